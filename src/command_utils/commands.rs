@@ -8,7 +8,7 @@ use std::{collections::HashMap, io::{Write, BufRead}};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use crate::{context::{RotfContext, ContextState}, credits, cutscene::RotfCutscene};
+use crate::{context::{RotfContext, ContextState}, credits, cutscene::RotfCutscene, options::AutosavePolicy};
 
 pub fn parse_command<R, W, E>(cmd: &str, context: &mut RotfContext<R, W, E>) where
   R: BufRead,
@@ -24,7 +24,15 @@ pub fn parse_command<R, W, E>(cmd: &str, context: &mut RotfContext<R, W, E>) whe
   let last_cmd = cmd_split.0.trim().to_lowercase();
   context.last_cmd = last_cmd.clone();
   context.last_params = cmd_split.1.trim().to_lowercase();
+  context.last_raw_params = cmd_split.1.trim().to_owned();
   let commands = context.commands.clone();
+  let last_state = context.curr_game.as_ref().map(|game| game.state.clone());
+  // dirty is cleared while the command runs to tell whether it changed the game
+  let last_turn = context.curr_game.as_mut().map(|game| {
+    let dirty = game.dirty;
+    game.dirty = false;
+    (game.environment.clock.ticks(), dirty)
+  });
   // Run command
   match commands.get(&last_cmd) {
     Some(cmd) => cmd.call(context),
@@ -32,13 +40,32 @@ pub fn parse_command<R, W, E>(cmd: &str, context: &mut RotfContext<R, W, E>) whe
   }
   // Play cutscene if relevant
   RotfCutscene::resolve_context(context);
-  // Update and save game
+  // Update and autosave game
   match &mut context.curr_game {
     Some(game) => {
+      // only commands that pass time or change the game count as turns
+      match last_turn {
+        Some((ticks, dirty)) => {
+          if game.dirty || ticks != game.environment.clock.ticks() {
+            game.turns_since_save += 1;
+          }
+          game.dirty |= dirty;
+        },
+        None => {},
+      }
       game.update(&context.unit_loader, &context.item_loader);
-      match game.save() {
-        Ok(()) => {},
-        Err(e) => context.print_error("saving game", &e),
+      let autosave = match context.options.autosave {
+        AutosavePolicy::EVERY_COMMAND => true,
+        AutosavePolicy::EVERY_N_TURNS => game.turns_since_save >= context.options.autosave_turns,
+        AutosavePolicy::STATE_CHANGE => last_state != Some(game.state.clone()),
+        AutosavePolicy::MANUAL => false,
+      };
+      // progress is never lost on exit, whatever the policy
+      if autosave || context.exit {
+        match game.save_if_dirty(&context.paths) {
+          Ok(_) => {},
+          Err(e) => context.print_error("saving game", &e),
+        }
       }
     },
     None => {},
@@ -56,11 +83,21 @@ pub enum Command {
   CREDITS,
   REPLAY,
   OPTIONS,
+  VALIDATE,
+  RELOAD,
   // ContextState::HOME Commands
   LAUNCH,
   DELETE,
+  RESTORE,
+  EXPORT,
+  IMPORT,
+  RENAME,
+  COPY,
+  INFO,
+  UNDELETE,
   // ContextState::INGAME Commands
   ME,
+  BESTIARY,
   SAVE,
   // GameState::ENVIRONMENT Commands
   VIEW,
@@ -69,6 +106,9 @@ pub enum Command {
   PICKUP,
   INVENTORY,
   DROP,
+  INSPECT,
+  TALK,
+  TRAVEL,
   // GameState::COMBAT Commands
   FLEE,
   ATTACK,
@@ -76,7 +116,8 @@ pub enum Command {
 
 impl Command {
   fn system_commands() -> Vec<Command> {
-    return vec![Command::LS, Command::HELP, Command::EXIT, Command::CREDITS, Command::REPLAY, Command::OPTIONS];
+    return vec![Command::LS, Command::HELP, Command::EXIT, Command::CREDITS, Command::REPLAY, Command::OPTIONS,
+      Command::VALIDATE, Command::RELOAD];
   }
   fn context_state_commands<R, W, E>(context: &mut RotfContext<R, W, E>) -> Vec<Command> where
     R: BufRead,
@@ -84,9 +125,11 @@ impl Command {
     E: Write,
   {
     match context.context_state {
-      ContextState::HOME => vec![Command::LAUNCH, Command::DELETE],
+      ContextState::HOME => vec![Command::LAUNCH, Command::DELETE, Command::RESTORE,
+        Command::EXPORT, Command::IMPORT, Command::RENAME, Command::COPY, Command::INFO,
+        Command::UNDELETE],
       ContextState::INGAME => {
-        let mut context_cmds = vec![Command::ME, Command::SAVE];
+        let mut context_cmds = vec![Command::ME, Command::BESTIARY, Command::SAVE];
         let mut game_cmds = match &context.curr_game {
           Some(game) => game.commands(),
           None => vec![],
@@ -106,11 +149,21 @@ impl Command {
       Command::CREDITS => "credits",
       Command::REPLAY => "replay",
       Command::OPTIONS => "options",
+      Command::VALIDATE => "validate",
+      Command::RELOAD => "reload",
       // ContextState::HOME Commands
       Command::LAUNCH => "launch",
       Command::DELETE => "delete",
+      Command::RESTORE => "restore",
+      Command::EXPORT => "export",
+      Command::IMPORT => "import",
+      Command::RENAME => "rename",
+      Command::COPY => "copy",
+      Command::INFO => "info",
+      Command::UNDELETE => "undelete",
       // ContextState::INGAME Commands
       Command::ME => "me",
+      Command::BESTIARY => "bestiary",
       Command::SAVE => "save",
       // GameState::ENVIRONMENT Commands
       Command::VIEW => "view",
@@ -119,6 +172,9 @@ impl Command {
       Command::PICKUP => "pickup",
       Command::INVENTORY => "inventory",
       Command::DROP => "drop",
+      Command::INSPECT => "inspect",
+      Command::TALK => "talk",
+      Command::TRAVEL => "travel",
       // GameState::COMBAT Commands
       _ => "",
     }
@@ -132,11 +188,21 @@ impl Command {
       Command::CREDITS => "Display the credits",
       Command::REPLAY => "Replay last cutscene",
       Command::OPTIONS => "Opens the options menu",
+      Command::VALIDATE => "Check the data files for problems",
+      Command::RELOAD => "Reload the data files without restarting",
       // ContextState::HOME Commands
       Command::LAUNCH => "Launches a new or saved game",
       Command::DELETE => "Delete the specified saved game",
+      Command::RESTORE => "Restore a saved game from a backup",
+      Command::EXPORT => "Export a saved game to a json file",
+      Command::IMPORT => "Import a saved game from a json file",
+      Command::RENAME => "Rename a saved game",
+      Command::COPY => "Copy a saved game to a new name",
+      Command::INFO => "View a saved game without launching it",
+      Command::UNDELETE => "Bring back a deleted saved game",
       // ContextState::INGAME Commands
       Command::ME => "Display info about the current player",
+      Command::BESTIARY => "List the units and items you have encountered",
       Command::SAVE => "Save your progress and return to the main menu",
       // GameState::ENVIRONMENT Commands
       Command::VIEW => "View your surroundings",
@@ -145,6 +211,9 @@ impl Command {
       Command::PICKUP => "Pickup the specified item in your view",
       Command::INVENTORY => "View your inventory",
       Command::DROP => "Drop an item",
+      Command::INSPECT => "Look closely at a unit or item",
+      Command::TALK => "Talk to the specified NPC in your view",
+      Command::TRAVEL => "Travel to another biome",
       // GameState::COMBAT Commands
       _ => "Not implemented",
     }
@@ -182,29 +251,84 @@ impl Command {
         context.println("Opens the options menu, where options can be saved");
         context.println("Options will be persistent across saves");
       },
+      Command::VALIDATE => {
+        context.println("Loads every data file, NPC dialogue and cutscene and lists the problems found");
+        context.println("Errors stop data from loading or working as intended; warnings are things like unnamed ids");
+        context.println("Content packs in the data folder's mods folder are checked along with the base data");
+        context.println("The same check can be run without starting the game with 'rise_of_the_frogs --validate'");
+      },
+      Command::RELOAD => {
+        context.println("Checks the data files like 'validate' and, if there are no errors, reads them again");
+        context.println("Lists the unit and item ids that were added, changed or removed");
+        context.println("Units and items already in the game keep their ids, so ones whose data was removed show as unknown");
+        context.println("Cutscenes are read each time they play, so changes to them don't need a reload");
+      },
       // ContextState::HOME Commands
       Command::LAUNCH => {
         context.println("Usage: 'launch {{arg}}'");
         context.println("Launch a new game with 'launch new'");
         context.println("Launch a saved game with 'launch {{saved_game_name}}");
         context.println("View the list of saved games with 'launch ls'");
+        context.println("Saved games can be named by their name or by the id shown in brackets");
       },
       Command::DELETE => {
         context.println("Usage: 'delete {{arg}}'");
-        context.println("Delete an existing saved game permanently");
+        context.println("Delete an existing saved game after asking for confirmation");
+        context.println("Skip the confirmation with 'delete {{arg}} --yes'");
+        context.println("Deleted saved games are kept in the trash; its size can be changed in the options menu");
+      },
+      Command::RESTORE => {
+        context.println("Usage: 'restore {{saved_game_name}} {{generation}}'");
+        context.println("Replace a saved game with one of its backups, where generation 1 is the most recent");
+        context.println("List the backups of a saved game with 'restore {{saved_game_name}}'");
+      },
+      Command::EXPORT => {
+        context.println("Usage: 'export {{saved_game_name}}'");
+        context.println("Write a saved game to {{saved_game_name}}.json in the exports folder of the save directory");
+      },
+      Command::IMPORT => {
+        context.println("Usage: 'import {{name}}'");
+        context.println("Create a saved game from {{name}}.json in the exports folder of the save directory");
+        context.println("The new game is written in the save format chosen in the options menu");
+      },
+      Command::RENAME => {
+        context.println("Usage: 'rename {{saved_game_name}} {{new_name}}'");
+        context.println("Put names with spaces in double quotes");
+        context.println("The backups of the saved game are deleted");
+      },
+      Command::COPY => {
+        context.println("Usage: 'copy {{saved_game_name}} {{new_name}}'");
+        context.println("Put names with spaces in double quotes");
+        context.println("The copy starts without backups");
+      },
+      Command::INFO => {
+        context.println("Usage: 'info {{saved_game_name}}'");
+        context.println("Displays the difficulty, player and time of a saved game without launching it");
+      },
+      Command::UNDELETE => {
+        context.println("Usage: 'undelete {{saved_game_name}}'");
+        context.println("Bring back the most recently deleted saved game with that name from the trash");
+        context.println("List the saved games in the trash with 'undelete'");
       },
       // ContextState::INGAME Commands
       Command::ME => {
         context.println("Displays info about the player");
       },
+      Command::BESTIARY => {
+        context.println("Usage: 'bestiary' or 'bestiary {{tier}}'");
+        context.println("Lists every unit and item you have seen, with how often you saw, fought or picked it up");
+        context.println("Stats are revealed for units you have fought and items you have picked up");
+        context.println("Give a tier to only list units and items from that tier");
+      },
       Command::SAVE => {
         context.println("Saves your progress and returns to the main menu");
-        context.println("Since the game saves itself as you play, this command is more so you can switch save games");
+        context.println("How often the game saves itself as you play is set by the autosave option; it always saves on exit");
       },
       // GameState::ENVIRONMENT Commands
       Command::VIEW => {
         context.println("View your current surroundings");
         context.println("You will see things based on how far you can view");
+        context.println("The current time, season and weather are shown first");
       },
       Command::WAIT => {
         context.println("Wait for time to pass");
@@ -228,6 +352,23 @@ impl Command {
         context.println("Arg is the index of the inventory item to drop");
         context.println("You can see the index of your inventory items with 'inventory'");
       },
+      Command::INSPECT => {
+        context.println("Usage: 'inspect {{arg}}', 'inspect item {{arg}}' or 'inspect inv {{arg}}'");
+        context.println("Arg is the index of a viewable unit, a viewable item or an inventory item");
+        context.println("You see more about things at or below your level; stats of much stronger things stay unknown");
+      },
+      Command::TALK => {
+        context.println("Usage: 'talk {{arg}}'");
+        context.println("Arg is the index of the viewable NPC to talk to");
+        context.println("Pick a reply by entering its number, or enter '0' to walk away");
+        context.println("Some replies are only offered once you reach a level or carry a certain item");
+      },
+      Command::TRAVEL => {
+        context.println("Usage: 'travel {{biome}}'");
+        context.println("Biomes are POND, STREAM, MARSH, FOREST_FLOOR and UNDERGROUND");
+        context.println("Units, items and NPCs are left behind and the new biome's spawn instead");
+        context.println("The biome you are in is shown first by 'view'");
+      },
       // GameState::COMBAT Commands
       _ => {
         context.println("Not implemented.");
@@ -244,6 +385,7 @@ impl Command {
       Command::FIGHT => vec!["fi"],
       Command::PICKUP => vec!["pu"],
       Command::INVENTORY => vec!["inv"],
+      Command::INSPECT => vec!["examine"],
       _ => vec![],
     }
   }
@@ -260,15 +402,26 @@ impl Command {
       Command::CREDITS => credits::credits(context),
       Command::REPLAY => system_commands::replay(context),
       Command::OPTIONS => system_commands::options(context),
+      Command::VALIDATE => system_commands::validate(context),
+      Command::RELOAD => system_commands::reload(context),
       // ContextState::HOME Commands
       Command::LAUNCH => context_state_commands::launch(context),
       Command::DELETE => context_state_commands::delete(context),
+      Command::RESTORE => context_state_commands::restore(context),
+      Command::EXPORT => context_state_commands::export(context),
+      Command::IMPORT => context_state_commands::import(context),
+      Command::RENAME => context_state_commands::rename(context),
+      Command::COPY => context_state_commands::copy(context),
+      Command::INFO => context_state_commands::info(context),
+      Command::UNDELETE => context_state_commands::undelete(context),
       // ContextState::INGAME Commands
       Command::ME => context_state_commands::me(context),
+      Command::BESTIARY => context_state_commands::bestiary(context),
       Command::SAVE => context_state_commands::save(context),
       // GameState::ENVIRONMENT Commands
       Command::VIEW | Command::WAIT |  Command::FIGHT | Command::PICKUP |
-      Command::INVENTORY | Command::DROP => {
+      Command::INVENTORY | Command::DROP | Command::INSPECT | Command::TALK |
+      Command::TRAVEL => {
         environment_commands::command(context, self.name());
       },
       // GameState::COMBAT Commands
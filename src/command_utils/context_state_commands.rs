@@ -1,6 +1,7 @@
-use crate::{filesystem, context::{self, RotfContext, ContextState}, game::{self, RotfDifficulty}};
+use crate::{filesystem, context::{self, RotfContext, ContextState}, game::{self, RotfDifficulty, save_writer, trash,
+  save_backend::{JsonBackend, SaveFormat}, save_slot::{self, SaveSlot}}};
 
-use std::{io::{Write, Error, BufRead}, path::PathBuf, ffi::OsStr};
+use std::io::{Write, Error, ErrorKind, BufRead};
 
 
 // Launch a saved or new game
@@ -28,34 +29,294 @@ pub fn launch<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
 }
 
 
-// Delete a saved game
+// Delete a saved game by moving it to the trash
 pub fn delete<R, W, E>(context: &mut RotfContext<R, W, E>) where
   R: BufRead,
   W: Write,
   E: Write,
 {
-  if context.last_params.is_empty() {
+  let mut params = context.last_raw_params.trim().to_owned();
+  let confirmed = params == "--yes" || params.ends_with(" --yes");
+  if confirmed {
+    params = params[..params.len() - "--yes".len()].trim().to_owned();
+  }
+  if params.is_empty() {
     context.println("Must specify a saved game to delete");
     context.println("You can view the current saved games with 'launch ls'");
     return;
   }
-  match get_saved_games() {
-    Ok(entries) => {
-      for entry in entries {
-        let save_name = entry.file_name().unwrap_or_else(|| OsStr::new(""))
-          .to_string_lossy().trim().to_lowercase();
-        let delete_name = str::replace(&context.last_params, " ", "_");
-        if save_name == delete_name {
-          filesystem::delete_folder(format!("data/saves/{}", delete_name)).unwrap_or_else(|e| {
-            context.print_error("deleting saved game", &e);
-          });
+  let slot = match find_save(context, &params) {
+    Some(slot) => slot,
+    None => return,
+  };
+  if !confirmed {
+    context.print(&format!("Are you sure you want to delete {}? (y/n) ", slot.name));
+    match context.read_line() {
+      Ok(input) => match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => {},
+        _ => {
+          context.println("Did not delete saved game");
           return;
-        }
+        },
+      },
+      Err(e) => {
+        context.print_error("reading input", &e);
+        return;
+      },
+    }
+  }
+  match trash::trash_save(&context.paths, &slot.id, context.options.trash_size) {
+    Ok(()) => {
+      if context.options.trash_size > 0 {
+        context.println(&format!("Deleted {}; use 'undelete {}' to bring it back", slot.name, slot.id));
+      }
+      else {
+        context.println(&format!("Deleted {}", slot.name));
+      }
+    },
+    Err(e) => context.print_error("deleting saved game", &e),
+  }
+}
+
+
+// Bring back a saved game from the trash
+pub fn undelete<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let entries = match trash::entries(&context.paths) {
+    Ok(entries) => entries,
+    Err(e) => {
+      context.print_error("finding deleted saved games", &e);
+      return;
+    },
+  };
+  if context.last_raw_params.is_empty() {
+    if entries.is_empty() {
+      context.println("The trash is empty");
+      return;
+    }
+    context.println("Deleted saved games (most recent last):");
+    for (_, id) in entries {
+      let name = save_slot::decode(&id).unwrap_or(id.clone());
+      context.println(&format!("  {}", SaveSlot { id, name }.label()));
+    }
+    context.println("Use 'undelete {{saved_game_name}}' to bring one back");
+    return;
+  }
+  let query = context.last_raw_params.clone();
+  let id = match entries.into_iter().rev().map(|(_, id)| id).find(|id| id == &query ||
+    save_slot::decode(id).map(|name| name.to_lowercase() == query.to_lowercase()).unwrap_or(false)) {
+    Some(id) => id,
+    None => {
+      context.println("Saved game isn't in the trash");
+      return;
+    },
+  };
+  match trash::undelete(&context.paths, &id) {
+    Ok(()) => context.println(&format!("Brought back {}", query)),
+    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+      context.println("That saved game already exists");
+      context.println(&format!("To delete it use 'delete {}'.", id));
+    },
+    Err(e) => context.print_error("bringing back saved game", &e),
+  }
+}
+
+
+// Restore a saved game from one of its backups
+pub fn restore<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  if context.last_raw_params.is_empty() {
+    context.println("Must specify a saved game to restore");
+    context.println("Use 'restore {{saved_game_name}} {{generation}}' to restore a backup");
+    return;
+  }
+  let params = context.last_raw_params.clone();
+  let (name, generation) = match params.rsplit_once(" ") {
+    Some((name, generation)) => match generation.parse::<usize>() {
+      Ok(g) => (name.to_owned(), Some(g)),
+      Err(_) => (params.clone(), None),
+    },
+    None => (params.clone(), None),
+  };
+  let slot = match find_save(context, &name) {
+    Some(slot) => slot,
+    None => return,
+  };
+  let generations = save_writer::generations(&context.paths, &slot.id);
+  match generation {
+    Some(g) => {
+      if !generations.contains(&g) {
+        context.println(&format!("Backup generation {} doesn't exist", g));
+        return;
+      }
+      match save_writer::restore(&context.paths, &slot.id, g) {
+        Ok(()) => context.println(&format!("Restored {} from backup generation {}", slot.name, g)),
+        Err(e) => context.print_error("restoring saved game", &e),
+      }
+    },
+    None => {
+      if generations.is_empty() {
+        context.println(&format!("{} has no backups", slot.name));
+        return;
+      }
+      context.println(&format!("Backups of {} (1 is the most recent):", slot.name));
+      for g in generations {
+        context.println(&format!("  {}", g));
+      }
+    },
+  }
+}
+
+
+// Export a saved game to a single json file
+pub fn export<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  if context.last_raw_params.is_empty() {
+    context.println("Must specify a saved game to export");
+    context.println("You can view the current saved games with 'launch ls'");
+    return;
+  }
+  let slot = match find_save(context, &context.last_raw_params.clone()) {
+    Some(slot) => slot,
+    None => return,
+  };
+  let contents = match game::RotfGame::load(&context.paths, &slot.id).and_then(|game| JsonBackend::to_json(&game)) {
+    Ok(contents) => contents,
+    Err(e) => {
+      context.print_error("exporting saved game", &e);
+      return;
+    },
+  };
+  let path = context.paths.export_file(&slot.id);
+  match filesystem::create_folder(context.paths.exports())
+    .and_then(|_| filesystem::create_file(path.clone(), contents)) {
+    Ok(()) => context.println(&format!("Exported {} to {}", slot.name, path)),
+    Err(e) => context.print_error("exporting saved game", &e),
+  }
+}
+
+
+// Import a saved game from a single json file
+pub fn import<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  if context.last_raw_params.is_empty() {
+    context.println("Must specify an exported game to import");
+    context.println("Use 'import {{name}}' to import {{name}}.json from the exports folder");
+    return;
+  }
+  let name = context.last_raw_params.clone();
+  let mut path = context.paths.export_file(&name);
+  if !std::path::Path::new(&path).exists() {
+    path = context.paths.export_file(&save_slot::slot_id(&name));
+  }
+  let contents = match filesystem::read_file(path.clone()) {
+    Ok(contents) => contents,
+    Err(_) => {
+      context.println(&format!("{} doesn't exist", path));
+      return;
+    },
+  };
+  let mut game = match JsonBackend::from_json(&path, &contents) {
+    Ok(game) => game,
+    Err(e) => {
+      context.print_error("importing saved game", &e);
+      return;
+    },
+  };
+  match validate_name(&game.name) {
+    Ok(()) => {},
+    Err(message) => {
+      context.println(&message);
+      return;
+    },
+  }
+  if !name_available(context, &game.name) {
+    return;
+  }
+  game.slot = save_slot::slot_id(&game.name);
+  game.save_format = context.options.save_format.clone();
+  match game.save(&context.paths) {
+    Ok(()) => context.println(&format!("Imported {} as a {} save", game.name, game.save_format)),
+    Err(e) => context.print_error("importing saved game", &e),
+  }
+}
+
+
+// Rename a saved game
+pub fn rename<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let (slot, new_name) = match new_name_params(context, "rename") {
+    Some(params) => params,
+    None => return,
+  };
+  match save_slot::rename(&context.paths, &slot, &new_name) {
+    Ok(renamed) => context.println(&format!("Renamed {} to {}", slot.name, renamed.name)),
+    Err(e) => context.print_error("renaming saved game", &e),
+  }
+}
+
+
+// Copy a saved game to a new name
+pub fn copy<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  match copy_save(context, "copy") {
+    Some((slot, game)) => context.println(&format!("Copied {} to {}", slot.name, game.name)),
+    None => {},
+  }
+}
+
+
+// Display info on a saved game without launching it
+pub fn info<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  if context.last_raw_params.is_empty() {
+    context.println("Must specify a saved game to view");
+    context.println("You can view the current saved games with 'launch ls'");
+    return;
+  }
+  let slot = match find_save(context, &context.last_raw_params.clone()) {
+    Some(slot) => slot,
+    None => return,
+  };
+  let format = SaveFormat::detect(&context.paths, &slot.id);
+  match format.backend().peek(&context.paths, &slot.id) {
+    Ok(game) => {
+      let mut info = format!("Saved Game: {}", slot.label());
+      info += &format!("\n   Difficulty: {}", game.difficulty);
+      info += &format!("\n   State: {}", game.state);
+      info += &format!("\n   Format: {}", format);
+      info += &format!("\n   Backups: {}", save_writer::generations(&context.paths, &slot.id).len());
+      info += &format!("\n{}", game.player.me(game.name.clone()));
+      context.println(&info);
+    },
+    Err(e) => {
+      context.print_error("loading game", &e);
+      if e.kind() == ErrorKind::InvalidData {
+        context.println("The saved game is corrupted");
       }
     },
-    Err(e) => context.print_error("finding saved games", &e),
   }
-  context.println("Saved game doesn't exist");
 }
 
 
@@ -68,7 +329,8 @@ pub fn me<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   match &context.curr_game {
     Some(game) => {
       let name = game.name.clone();
-      let player = game.player.me(name);
+      let mut player = game.player.me(name);
+      player += &format!("\n   Time: {}", game.environment.clock);
       context.println(&player);
     }
     None => {
@@ -79,6 +341,31 @@ pub fn me<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
 }
 
 
+pub fn bestiary<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let tier = match context.last_params.trim() {
+    "" => None,
+    param => match param.parse::<u8>() {
+      Ok(tier) if tier > 0 => Some(tier),
+      _ => {
+        context.println("Invalid tier. Must be a positive integer");
+        return;
+      },
+    },
+  };
+  match &context.curr_game {
+    Some(game) => {
+      let bestiary = game.player.bestiary.view(tier, &context.unit_loader, &context.item_loader);
+      context.println(&bestiary);
+    },
+    None => context.eprintln("Can't use BESTIARY when there's no game"),
+  }
+}
+
+
 // Saves game and returns to main menu
 pub fn save<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   R: BufRead,
@@ -87,7 +374,7 @@ pub fn save<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
 {
   match &context.curr_game {
     Some(game) => {
-      match game.save() {
+      match game.save(&context.paths) {
         Ok(()) => {
           context.curr_game = None;
           context.context_state = ContextState::HOME;
@@ -121,51 +408,15 @@ fn launch_new<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
       return;
     },
   }
-  name = name.trim().to_lowercase();
-  if name.is_empty() {
-    context.println("Can't enter empty name");
-    return;
-  }
-  let invalid_characters = "-<>:\"\\/|?*^";
-  for char in invalid_characters.chars() {
-    if name.contains(char) {
-      context.println(format!("Cannot use the following characters: {}", invalid_characters).as_str());
-      return;
-    }
-  }
-  let invalid_names = vec!["com1", "com2", "com3", "com4", "com5", "com6",
-    "com7", "com8", "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7",
-    "lpt8", "lpt9", "con", "nul", "prn"];
-  for invalid_name in invalid_names {
-    if name == invalid_name {
-      context.println(format!("Cannot use the following name: {}", invalid_name).as_str());
-      return;
-    }
-  }
-  if name.len() > 30 {
-    context.println("Cannot enter a name of more than 30 characters");
-    return;
-  }
-  if name.starts_with(".") || name.ends_with(".") {
-    context.println("Cannot start or end name with a '.'");
-    return;
-  }
-  let mut current_games = Vec::new();
-  match filesystem::open_folder_or_create("data/saves".to_owned()) {
-    Ok(games) => {
-      for game in games.iter() {
-        current_games.push(game.file_name().unwrap_or_else(|| OsStr::new(""))
-          .to_string_lossy().trim().to_lowercase());
-      }
-    }
-    Err(e) => {
-      context.print_error("Error finding saved games: {}", &e);
+  name = name.trim().to_owned();
+  match validate_name(&name) {
+    Ok(()) => {},
+    Err(message) => {
+      context.println(&message);
       return;
-    }
+    },
   }
-  if current_games.contains(&name) {
-    context.println("That saved game already exists");
-    context.println(&format!("To delete it use 'delete {}'.", name));
+  if !name_available(context, &name) {
     context.println(&format!("To launch it use 'launch {}'.", name));
     return;
   }
@@ -177,8 +428,9 @@ fn launch_new<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
       return;
     }
   }
-  let new_game = game::RotfGame::new(name.clone(), difficulty);
-  match new_game.save() {
+  let mut new_game = game::RotfGame::new(name.clone(), difficulty);
+  new_game.save_format = context.options.save_format.clone();
+  match new_game.save(&context.paths) {
     Ok(()) => {},
     Err(e) => {
       context.print_error("creating new game", &e);
@@ -221,11 +473,10 @@ fn launch_ls<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   W: Write,
   E: Write,
 {
-  match get_saved_games() {
-    Ok(entries) => {
-      for entry in entries {
-        let save_name = entry.file_name().unwrap_or_else(|| OsStr::new(""));
-        context.println(&format!("{}", save_name.to_string_lossy().to_lowercase()));
+  match save_slot::list(&context.paths) {
+    Ok(slots) => {
+      for slot in slots {
+        context.println(&slot.label());
       }
     },
     Err(e) => context.print_error("Error finding saved games: {}", &e),
@@ -237,42 +488,163 @@ fn launch_load<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   W: Write,
   E: Write,
 {
-  match get_saved_games() {
-    Ok(entries) => {
-      for entry in entries {
-        let save_name = entry.file_name().unwrap_or_else(|| OsStr::new(""))
-          .to_string_lossy().trim().to_lowercase();
-        if &save_name == &context.last_params {
-          match game::RotfGame::load(save_name) {
-            Ok(game) => {
-              context.launch_game(game, false);
-              me(context);
-            },
-            Err(e) => {
-              context.print_error("loading game", &e);
-            },
-          }
-          return;
-        }
+  let slot = match find_save(context, &context.last_raw_params.clone()) {
+    Some(slot) => slot,
+    None => return,
+  };
+  match game::RotfGame::load(&context.paths, &slot.id) {
+    Ok(game) => {
+      context.launch_game(game, false);
+      me(context);
+    },
+    Err(e) => {
+      context.print_error("loading game", &e);
+      if e.kind() == ErrorKind::InvalidData {
+        context.println("The saved game is corrupted and was not loaded");
       }
     },
-    Err(e) => context.print_error("finding saved games", &e),
   }
-  context.println("Saved game doesn't exist");
 }
 
-fn get_saved_games() -> Result<Vec<PathBuf>, Error> {
-  let mut saved_games = Vec::new();
-  match filesystem::open_folder_or_create("data/saves".to_owned()) {
-    Ok(entries) => {
-      for entry in entries {
-        if entry.is_dir() {
-          saved_games.push(entry);
+// Checks a name can be used for a saved game, returning the reason it can't otherwise
+fn validate_name(name: &str) -> Result<(), String> {
+  if name.is_empty() {
+    return Err("Can't enter empty name".to_owned());
+  }
+  let invalid_characters = "-<>:\"\\/|?*^";
+  for char in invalid_characters.chars() {
+    if name.contains(char) {
+      return Err(format!("Cannot use the following characters: {}", invalid_characters));
+    }
+  }
+  let invalid_names = vec!["com1", "com2", "com3", "com4", "com5", "com6",
+    "com7", "com8", "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7",
+    "lpt8", "lpt9", "con", "nul", "prn"];
+  for invalid_name in invalid_names {
+    if name.to_lowercase() == invalid_name {
+      return Err(format!("Cannot use the following name: {}", invalid_name));
+    }
+  }
+  if name.chars().count() > 30 {
+    return Err("Cannot enter a name of more than 30 characters".to_owned());
+  }
+  if name.starts_with(".") || name.ends_with(".") {
+    return Err("Cannot start or end name with a '.'".to_owned());
+  }
+  Ok(())
+}
+
+// Finds a saved game by slot id or name, printing why when it can't be found
+fn find_save<R, W, E>(context: &mut RotfContext<R, W, E>, query: &str) -> Option<SaveSlot> where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  match save_slot::find(&context.paths, query) {
+    Ok(Some(slot)) => Some(slot),
+    Ok(None) => {
+      context.println("Saved game doesn't exist");
+      None
+    },
+    Err(e) => {
+      context.print_error("finding saved games", &e);
+      None
+    },
+  }
+}
+
+// Checks no saved game already uses a name, printing why otherwise
+fn name_available<R, W, E>(context: &mut RotfContext<R, W, E>, name: &str) -> bool where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  match save_slot::name_taken(&context.paths, name) {
+    Ok(false) => true,
+    Ok(true) => {
+      context.println("That saved game already exists");
+      context.println(&format!("To delete it use 'delete {}'.", name));
+      false
+    },
+    Err(e) => {
+      context.print_error("finding saved games", &e);
+      false
+    },
+  }
+}
+
+// Splits params on spaces, except inside double quotes
+fn split_params(params: &str) -> Vec<String> {
+  let mut split = Vec::new();
+  let mut current = String::new();
+  let mut quoted = false;
+  for c in params.chars() {
+    match c {
+      '"' => quoted = !quoted,
+      ' ' if !quoted => {
+        if !current.is_empty() {
+          split.push(current.clone());
+          current.clear();
         }
-      }
-      Ok(saved_games)
+      },
+      _ => current.push(c),
+    }
+  }
+  if !current.is_empty() {
+    split.push(current);
+  }
+  return split;
+}
+
+// Reads the saved game and the new name given to a command, printing why when they can't be used
+fn new_name_params<R, W, E>(context: &mut RotfContext<R, W, E>, cmd: &str) -> Option<(SaveSlot, String)> where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let params = split_params(&context.last_raw_params);
+  if params.len() != 2 {
+    context.println(&format!("Must specify a saved game and a new name when using {}", cmd));
+    context.println(&format!("Use '{} {{{{saved_game_name}}}} {{{{new_name}}}}', putting names with spaces in quotes", cmd));
+    return None;
+  }
+  let new_name = params[1].clone();
+  match validate_name(&new_name) {
+    Ok(()) => {},
+    Err(message) => {
+      context.println(&message);
+      return None;
+    },
+  }
+  let slot = find_save(context, &params[0])?;
+  if !name_available(context, &new_name) {
+    return None;
+  }
+  return Some((slot, new_name));
+}
+
+// Loads a saved game and writes it under a new name, returning the original slot and the new game
+fn copy_save<R, W, E>(context: &mut RotfContext<R, W, E>, cmd: &str) -> Option<(SaveSlot, game::RotfGame)> where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let (slot, new_name) = new_name_params(context, cmd)?;
+  let mut game = match game::RotfGame::load(&context.paths, &slot.id) {
+    Ok(game) => game,
+    Err(e) => {
+      context.print_error("loading game", &e);
+      return None;
+    },
+  };
+  game.slot = save_slot::slot_id(&new_name);
+  game.name = new_name;
+  match game.save(&context.paths) {
+    Ok(()) => Some((slot, game)),
+    Err(e) => {
+      context.print_error("saving game", &e);
+      None
     },
-    Err(e) => Err(e),
   }
 }
 
@@ -280,7 +652,15 @@ fn get_saved_games() -> Result<Vec<PathBuf>, Error> {
 #[cfg(test)]
 pub mod test_context_state_commands {
   use std::path::Path;
-  use crate::{test_main::*, commands::{context_state_commands::*, get_current_commands}, game::RotfGame, context::ContextState};
+  use rstest::*;
+  use crate::{test_main::*, commands::{context_state_commands::*, get_current_commands}, game::{RotfGame, save_backend::SaveFormat}, context::{ContextState, constants}};
+  use crate::filesystem;
+  use crate::game::GameState;
+  use crate::game::biome::Biome;
+  use crate::game::environment::Position;
+  use crate::game::npc::Npc;
+  use crate::game::traits::Positionable;
+  use crate::options::AutosavePolicy;
 
   #[test]
   fn test_launch() {
@@ -307,35 +687,89 @@ pub mod test_context_state_commands {
   fn test_launch_new() {
     let (output, error) = run_cmd_input("launch new", "test_new\n1\n");
     assert!(output.contains("Enter a name:"));
-    assert!(Path::new("data/saves/test_new").exists());
-    assert!(get_saved_games().unwrap().iter().any(|p| p.file_name().unwrap() == "test_new"));
+    assert!(Path::new(&test_paths().save("test%5Fnew")).exists());
+    assert!(save_slot::list(&test_paths()).unwrap().iter().any(|s| s.name == "test_new"));
     assert_eq!(error, "");
-    run_cmd_output("delete test_new"); // clean up test
+    run_cmd_output("delete test_new --yes"); // clean up test
   }
 
   #[test]
   fn test_launch_new_context() {
     let context = run_cmd_context_input("launch new", "test new context\n3\n");
-    assert!(Path::new("data/saves/test_new_context").exists());
-    assert!(get_saved_games().unwrap().iter().any(|p| p.file_name().unwrap() == "test_new_context"));
+    assert!(Path::new(&test_paths().save("test_new_context")).exists());
+    assert!(save_slot::list(&test_paths()).unwrap().iter().any(|s| s.id == "test_new_context"));
     let game = context.curr_game.unwrap();
     assert_eq!(game.name, "test new context");
     assert_eq!(game.difficulty, RotfDifficulty::HARD);
-    let saved_game = RotfGame::load("test new_context".to_owned()).unwrap();
+    let saved_game = RotfGame::load(&test_paths(), "test_new_context").unwrap();
     assert_eq!(saved_game.name, "test new context");
     assert_eq!(saved_game.difficulty, RotfDifficulty::HARD);
-    run_cmd_output("delete test_new_context"); // clean up test
+    run_cmd_output("delete test_new_context --yes"); // clean up test
   }
 
   #[test]
   fn test_launch_new_exists() {
-    assert!(Path::new("data/saves/test").exists());
+    assert!(Path::new(&test_paths().save("test")).exists());
     let (output, error) = run_cmd_input("launch new", "test\n");
     assert!(output.contains("Enter a name:"));
     assert!(output.contains("That saved game already exists"));
     assert_eq!(error, "");
   }
 
+  #[test]
+  fn test_launch_new_preserves_name() {
+    let context = run_cmd_context_input("launch new", "Test Case_Name\n2\n");
+    let (lowercase_output, _) = run_cmd_input("launch new", "test case_name\n");
+    let (ls_output, _) = run_cmd_output("launch ls");
+    let by_slot = run_cmd_context("launch %54est_%43ase%5F%4Eame");
+    let by_name = run_cmd_context("launch test case_name");
+    let saved_game = RotfGame::load(&test_paths(), "%54est_%43ase%5F%4Eame");
+    run_cmd_output("delete Test Case_Name --yes"); // clean up test
+    assert_eq!(context.curr_game.unwrap().name, "Test Case_Name");
+    assert!(lowercase_output.contains("That saved game already exists"));
+    assert!(ls_output.contains("Test Case_Name\n"));
+    assert_eq!(by_slot.curr_game.unwrap().name, "Test Case_Name");
+    assert_eq!(by_name.curr_game.unwrap().name, "Test Case_Name");
+    assert_eq!(saved_game.unwrap().name, "Test Case_Name");
+  }
+
+  #[test]
+  fn test_launch_underscores_and_spaces() {
+    RotfGame::new("test collide".to_owned(), RotfDifficulty::EASY).save(&test_paths()).unwrap();
+    RotfGame::new("test_collide".to_owned(), RotfDifficulty::HARD).save(&test_paths()).unwrap();
+    let spaces = run_cmd_context("launch test collide");
+    let by_id = run_cmd_context("launch test_collide");
+    let underscores = run_cmd_context("launch test%5Fcollide");
+    run_cmd_output("delete test collide --yes"); // clean up test
+    run_cmd_output("delete test%5Fcollide --yes");
+    assert_eq!(spaces.curr_game.unwrap().difficulty, RotfDifficulty::EASY);
+    assert_eq!(by_id.curr_game.unwrap().difficulty, RotfDifficulty::EASY);
+    assert_eq!(underscores.curr_game.unwrap().difficulty, RotfDifficulty::HARD);
+  }
+
+  #[test]
+  fn test_launch_with_broken_dialogue() {
+    let temp = TempRoot::new("broken_dialogue");
+    let (root, paths) = (temp.root(), &temp.paths);
+    for folder in ["units", "items", "cutscenes", "npcs"] {
+      filesystem::copy_folder(format!("data/{}", folder), format!("{}/{}", root, folder)).unwrap();
+    }
+    filesystem::create_file(paths.npc_file("BROKEN"), "name: Broken\nstart: GONE".to_owned()).unwrap();
+    let mut game = RotfGame::new("test broken dialogue".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.save(paths).unwrap();
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, paths.clone());
+    run_cmd("launch test broken dialogue", &mut context);
+    assert_eq!(context.context_state, ContextState::INGAME);
+    let npcs: Vec<String> = context.curr_game.unwrap().environment.npcs.values().map(|npc| npc.name.clone()).collect();
+    assert_eq!(npcs, vec!["Old Toad"]);
+    let error = std::str::from_utf8(&error).unwrap();
+    assert_eq!(error, format!("Error loading NPC dialogue: {}: 'start' names unknown node 'GONE'\n", paths.npc_file("BROKEN")));
+  }
+
   #[test]
   fn test_launch_game() {
     let context = run_cmd_context("launch test");
@@ -343,6 +777,57 @@ pub mod test_context_state_commands {
     assert_eq!(context.context_state, ContextState::INGAME);
   }
 
+  #[test]
+  fn test_launch_legacy_save() {
+    filesystem::create_folder(test_paths().save("test_legacy")).unwrap();
+    filesystem::create_file(test_paths().save_file("test_legacy", "metadata.rotf"),
+      "\nname: test_legacy\nstate: ENVIRONMENT\ndifficulty: EASY".to_owned()).unwrap();
+    filesystem::create_file(test_paths().save_file("test_legacy", "player.rotf"),
+      "\nlevel: 2\nview_distance: NEAR".to_owned()).unwrap();
+    filesystem::create_file(test_paths().save_file("test_legacy", "environment.rotf"),
+      "\ntime_passed: 0\n\nnext_unit_key: 4\n%%% BEGIN UNIT\n   id: 1\n   level: 1\n%%% END UNIT\n\nnext_unit_key: 5\n\nnext_unit_key: 1".to_owned()).unwrap();
+    let game = RotfGame::load(&test_paths(), "test_legacy").unwrap();
+    let metadata = filesystem::read_file(test_paths().save_file("test_legacy", "metadata.rotf")).unwrap();
+    let environment = filesystem::read_file(test_paths().save_file("test_legacy", "environment.rotf")).unwrap();
+    run_cmd_output("delete test_legacy --yes"); // clean up test
+    assert_eq!(game.player.level, 2);
+    assert_eq!(game.difficulty, RotfDifficulty::EASY);
+    assert!(game.environment.units.contains_key(&4));
+    assert_eq!(game.environment.next_unit_key, 5);
+    assert_eq!(game.environment.next_item_key, 1);
+    assert!(metadata.contains(&format!("version: {}", constants::SAVE_VERSION)));
+    assert!(environment.contains("weather: CLEAR"));
+    assert!(environment.contains("elite: false"));
+  }
+
+  #[test]
+  fn test_launch_newer_save() {
+    filesystem::create_folder(test_paths().save("test_newer")).unwrap();
+    filesystem::create_file(test_paths().save_file("test_newer", "metadata.rotf"),
+      format!("\nversion: {}\nname: test_newer", constants::SAVE_VERSION + 1)).unwrap();
+    filesystem::create_file(test_paths().save_file("test_newer", "player.rotf"), "".to_owned()).unwrap();
+    filesystem::create_file(test_paths().save_file("test_newer", "environment.rotf"), "".to_owned()).unwrap();
+    let (_, error) = run_cmd_output("launch test_newer");
+    run_cmd_output("delete test_newer --yes"); // clean up test
+    assert!(error.contains("is newer than the latest supported version"));
+  }
+
+  #[test]
+  fn test_launch_corrupted_save() {
+    filesystem::create_folder(test_paths().save("test_corrupted")).unwrap();
+    filesystem::create_file(test_paths().save_file("test_corrupted", "metadata.rotf"),
+      format!("\nversion: {}\nname: test_corrupted", constants::SAVE_VERSION)).unwrap();
+    filesystem::create_file(test_paths().save_file("test_corrupted", "player.rotf"),
+      "\nlevel: 2\nview_distance: NEAR\n%%% BEGIN ITEM\n   key: 1\n   id: 1\n   level: lots\n%%% END ITEM".to_owned()).unwrap();
+    filesystem::create_file(test_paths().save_file("test_corrupted", "environment.rotf"), "".to_owned()).unwrap();
+    let context = run_cmd_context("launch test_corrupted");
+    let (output, error) = run_cmd_output("launch test_corrupted");
+    run_cmd_output("delete test_corrupted --yes"); // clean up test
+    assert_eq!(context.context_state, ContextState::HOME);
+    assert!(output.contains("The saved game is corrupted and was not loaded"));
+    assert!(error.contains("player.rotf line 7: key 'level' expected an integer from 0 to 255, found 'lots'"));
+  }
+
   #[test]
   fn test_delete() {
     let (output, error) = run_cmd_output("delete");
@@ -352,7 +837,7 @@ pub mod test_context_state_commands {
 
   #[test]
   fn test_delete_unknown() {
-    let (output, error) = run_cmd_output("delete unknown");
+    let (output, error) = run_cmd_output("delete unknown --yes");
     assert!(output.contains("Saved game doesn't exist"));
     assert_eq!(error, "");
   }
@@ -360,13 +845,280 @@ pub mod test_context_state_commands {
   #[test]
   fn test_delete_game() {
     run_cmd_input("launch new", "deletable with spaces\n2\n");
-    assert!(get_saved_games().unwrap().iter().any(|p| p.file_name().unwrap() == "deletable_with_spaces"));
-    let (output, error) = run_cmd_output("delete deletable with_spaces");
-    assert!(!get_saved_games().unwrap().iter().any(|p| p.file_name().unwrap() == "deletable_with_spaces"));
-    assert_eq!(output, "");
+    assert!(save_slot::list(&test_paths()).unwrap().iter().any(|s| s.id == "deletable_with_spaces"));
+    let (output, error) = run_cmd_output("delete deletable with spaces --yes");
+    assert!(!save_slot::list(&test_paths()).unwrap().iter().any(|s| s.id == "deletable_with_spaces"));
+    assert!(output.contains("Deleted deletable with spaces; use 'undelete deletable_with_spaces' to bring it back"));
+    assert_eq!(error, "");
+  }
+
+  #[rstest]
+  #[case::confirmed("y\n", false)]
+  #[case::declined("n\n", true)]
+  #[case::no_input("", true)]
+  fn test_delete_confirmation(#[case] input: &str, #[case] kept: bool) {
+    let name = format!("test delete confirmation {}", kept);
+    RotfGame::new(name.clone(), RotfDifficulty::default()).save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_input(&format!("delete {}", name), input);
+    let exists = Path::new(&test_paths().save(&save_slot::slot_id(&name))).exists();
+    run_cmd_output(&format!("delete {} --yes", name)); // clean up test
+    assert!(output.contains(&format!("Are you sure you want to delete {}? (y/n)", name)));
+    assert_eq!(exists, kept);
+    assert_eq!(output.contains("Did not delete saved game"), kept);
+    assert_eq!(error, "");
+  }
+
+  // Context with its own save folder so the trash isn't shared with other tests
+  #[test]
+  fn test_undelete() {
+    let temp = TempRoot::saves("undelete");
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, temp.paths.clone());
+    let mut game = RotfGame::new("test undelete".to_owned(), RotfDifficulty::default());
+    game.player.level = 6;
+    game.save(&context.paths).unwrap();
+    game.save(&context.paths).unwrap();
+    run_cmd("delete test undelete --yes", &mut context);
+    let deleted = Path::new(&context.paths.save("test_undelete")).exists();
+    run_cmd("undelete", &mut context);
+    run_cmd("undelete test undelete", &mut context);
+    let restored = RotfGame::load(&context.paths, "test_undelete").unwrap();
+    let generations = save_writer::generations(&context.paths, "test_undelete");
+    let trashed = trash::entries(&context.paths).unwrap();
+
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    assert!(!deleted);
+    assert!(output.contains("Deleted saved games (most recent last):\n  test undelete"));
+    assert!(output.contains("Brought back test undelete"));
+    assert_eq!(error, "");
+    assert_eq!(restored.player.level, 6);
+    assert_eq!(generations, vec![1]);
+    assert!(trashed.is_empty());
+  }
+
+  #[test]
+  fn test_trash_purge() {
+    let temp = TempRoot::saves("trash_purge");
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, temp.paths.clone());
+    context.options.trash_size = 2;
+    for i in 0..4 {
+      RotfGame::new(format!("test purge {}", i), RotfDifficulty::default()).save(&context.paths).unwrap();
+      run_cmd(&format!("delete test purge {} --yes", i), &mut context);
+    }
+    run_cmd("undelete test purge 0", &mut context);
+    let trashed = trash::entries(&context.paths).unwrap();
+
+    let output = std::str::from_utf8(&output).unwrap();
+    assert!(output.contains("Saved game isn't in the trash"));
+    assert_eq!(trashed, vec![(3, "test_purge_2".to_owned()), (4, "test_purge_3".to_owned())]);
+  }
+
+  #[test]
+  fn test_save_keeps_backups() {
+    let mut game = RotfGame::new("test backups".to_owned(), RotfDifficulty::default());
+    for level in 0..(constants::SAVE_BACKUP_GENERATIONS + 2) {
+      game.player.level = level as u8;
+      game.save(&test_paths()).unwrap();
+    }
+    let generations = save_writer::generations(&test_paths(), "test_backups");
+    let backup = filesystem::read_file(format!("{}/.backups/test_backups/1/player.rotf", test_paths().saves())).unwrap();
+    run_cmd_output("delete test backups --yes"); // clean up test
+    assert_eq!(generations, (1..=constants::SAVE_BACKUP_GENERATIONS).collect::<Vec<usize>>());
+    assert!(backup.contains(&format!("level: {}", constants::SAVE_BACKUP_GENERATIONS)));
+    assert!(!Path::new(&format!("{}/.backups/test_backups", test_paths().saves())).exists());
+  }
+
+  #[test]
+  fn test_restore() {
+    let mut game = RotfGame::new("test restore".to_owned(), RotfDifficulty::default());
+    game.player.level = 3;
+    game.save(&test_paths()).unwrap();
+    game.player.level = 4;
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("restore test restore 1");
+    let restored = RotfGame::load(&test_paths(), "test_restore").unwrap();
+    let generations = save_writer::generations(&test_paths(), "test_restore");
+    run_cmd_output("delete test restore --yes"); // clean up test
+    assert!(output.contains("Restored test restore from backup generation 1"));
+    assert_eq!(error, "");
+    assert_eq!(restored.player.level, 3);
+    assert_eq!(generations, vec![1, 2]);
+  }
+
+  #[test]
+  fn test_restore_unknown_generation() {
+    let game = RotfGame::new("test restore unknown".to_owned(), RotfDifficulty::default());
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("restore test restore unknown 2");
+    run_cmd_output("delete test restore unknown --yes"); // clean up test
+    assert!(output.contains("Backup generation 2 doesn't exist"));
+    assert_eq!(error, "");
+  }
+
+  #[test]
+  fn test_export_import() {
+    let mut game = RotfGame::new("test export".to_owned(), RotfDifficulty::default());
+    game.player.level = 7;
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("export test export");
+    run_cmd_output("delete test export --yes");
+    assert!(output.contains(&format!("Exported test export to {}", test_paths().export_file("test_export"))));
+    assert_eq!(error, "");
+
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    context.options.save_format = SaveFormat::JSON;
+    run_cmd("import test export", &mut context);
+
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    let imported = RotfGame::load(&test_paths(), "test_export");
+    let json_exists = Path::new(&test_paths().save_file("test_export", "save.json")).exists();
+    run_cmd_output("delete test export --yes"); // clean up test
+    std::fs::remove_file(test_paths().export_file("test_export")).unwrap();
+    assert!(output.contains("Imported test export as a JSON save"));
+    assert_eq!(error, "");
+    assert!(json_exists);
+    assert_eq!(imported.unwrap().player.level, 7);
+  }
+
+  #[test]
+  fn test_import_existing() {
+    let game = RotfGame::new("test import existing".to_owned(), RotfDifficulty::default());
+    game.save(&test_paths()).unwrap();
+    run_cmd_output("export test import existing");
+    let (output, error) = run_cmd_output("import test import existing");
+    run_cmd_output("delete test import existing --yes"); // clean up test
+    std::fs::remove_file(test_paths().export_file("test_import_existing")).unwrap();
+    assert!(output.contains("That saved game already exists"));
+    assert_eq!(error, "");
+  }
+
+  #[test]
+  fn test_import_invalid_name() {
+    let game = RotfGame::new("test import invalid".to_owned(), RotfDifficulty::default());
+    game.save(&test_paths()).unwrap();
+    run_cmd_output("export test import invalid");
+    run_cmd_output("delete test import invalid --yes");
+    let path = test_paths().export_file("test_import_invalid");
+    let contents = filesystem::read_file(path.clone()).unwrap();
+    std::fs::write(&path, contents.replace("\"test import invalid\"", "\"test|import|invalid\"")).unwrap();
+    let (output, error) = run_cmd_output("import test import invalid");
+    let imported = Path::new(&test_paths().save("test|import|invalid")).exists();
+    std::fs::remove_file(path).unwrap(); // clean up test
+    assert!(output.contains("Cannot use the following characters"));
+    assert_eq!(error, "");
+    assert!(!imported);
+  }
+
+  #[test]
+  fn test_rename() {
+    let mut game = RotfGame::new("test rename".to_owned(), RotfDifficulty::default());
+    game.save(&test_paths()).unwrap();
+    game.player.level = 5;
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("rename test_rename \"Test Renamed Game\"");
+    let renamed = RotfGame::load(&test_paths(), "%54est_%52enamed_%47ame");
+    let old_exists = Path::new(&test_paths().save("test_rename")).exists();
+    let old_backups_exist = Path::new(&save_writer::backups_path(&test_paths(), "test_rename")).exists();
+    let generations = save_writer::generations(&test_paths(), "%54est_%52enamed_%47ame");
+    let backup = filesystem::read_file(format!("{}/metadata.rotf",
+      save_writer::backup_path(&test_paths(), "%54est_%52enamed_%47ame", 1)));
+    run_cmd_output("delete Test Renamed Game --yes"); // clean up test
+    assert!(output.contains("Renamed test rename to Test Renamed Game"));
+    assert_eq!(error, "");
+    assert!(!old_exists);
+    assert!(!old_backups_exist);
+    assert_eq!(generations, vec![1]);
+    assert!(backup.unwrap().contains("\nname: Test Renamed Game"));
+    let renamed = renamed.unwrap();
+    assert_eq!(renamed.name, "Test Renamed Game");
+    assert_eq!(renamed.player.level, 5);
+  }
+
+  #[test]
+  fn test_rename_json() {
+    let mut game = RotfGame::new("test rename json".to_owned(), RotfDifficulty::default());
+    game.save_format = SaveFormat::JSON;
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("rename \"test rename json\" test_renamed_json");
+    let renamed = RotfGame::load(&test_paths(), "test%5Frenamed%5Fjson");
+    run_cmd_output("delete test_renamed_json --yes"); // clean up test
+    assert!(output.contains("Renamed test rename json to test_renamed_json"));
+    assert_eq!(error, "");
+    let renamed = renamed.unwrap();
+    assert_eq!(renamed.name, "test_renamed_json");
+    assert_eq!(renamed.save_format, SaveFormat::JSON);
+  }
+
+  #[test]
+  fn test_copy() {
+    let game = RotfGame::new("test copy".to_owned(), RotfDifficulty::HARD);
+    game.save(&test_paths()).unwrap();
+    let (output, error) = run_cmd_output("copy \"test copy\" \"test copied\"");
+    let original = RotfGame::load(&test_paths(), "test_copy");
+    let copied = RotfGame::load(&test_paths(), "test_copied");
+    run_cmd_output("delete test copy --yes"); // clean up test
+    run_cmd_output("delete test copied --yes");
+    assert!(output.contains("Copied test copy to test copied"));
+    assert_eq!(error, "");
+    assert_eq!(original.unwrap().name, "test copy");
+    assert_eq!(copied.unwrap().difficulty, RotfDifficulty::HARD);
+  }
+
+  #[rstest]
+  #[case::missing_name("copy test", "Must specify a saved game and a new name when using copy")]
+  #[case::unknown("copy unknown test_copy_unknown", "Saved game doesn't exist")]
+  #[case::exists("copy test test", "That saved game already exists")]
+  #[case::invalid_name("rename test con", "Cannot use the following name: con")]
+  fn test_copy_invalid(#[case] cmd: &str, #[case] expected: &str) {
+    let (output, error) = run_cmd_output(cmd);
+    assert!(output.contains(expected));
+    assert_eq!(error, "");
+  }
+
+  #[test]
+  fn test_info() {
+    let mut game = RotfGame::new("test info".to_owned(), RotfDifficulty::EASY);
+    game.player.level = 9;
+    game.save(&test_paths()).unwrap();
+    let context = run_cmd_context("info test info");
+    let (output, error) = run_cmd_output("info test info");
+    run_cmd_output("delete test info --yes"); // clean up test
+    assert_eq!(context.context_state, ContextState::HOME);
+    assert!(output.contains("Saved Game: test info"));
+    assert!(output.contains("Difficulty: EASY"));
+    assert!(output.contains("Format: ROTF"));
+    assert!(output.contains("Level: 9"));
     assert_eq!(error, "");
   }
 
+  #[test]
+  fn test_info_does_not_migrate() {
+    let game = RotfGame::new("test info old".to_owned(), RotfDifficulty::default());
+    game.save(&test_paths()).unwrap();
+    let metadata = test_paths().save_file("test_info_old", "metadata.rotf");
+    let old = filesystem::read_file(metadata.clone()).unwrap()
+      .replace(&format!("version: {}", constants::SAVE_VERSION), "version: 1");
+    filesystem::create_file(metadata.clone(), old.clone()).unwrap();
+    let (output, error) = run_cmd_output("info test info old");
+    let after = filesystem::read_file(metadata).unwrap();
+    let generations = save_writer::generations(&test_paths(), "test_info_old");
+    run_cmd_output("delete test info old --yes"); // clean up test
+    assert!(output.contains("Saved Game: test info old"));
+    assert_eq!(error, "");
+    assert_eq!(after, old);
+    assert!(generations.is_empty());
+  }
+
   #[test]
   fn test_me_when_home() {
     let (output, error) = run_cmd_output("me");
@@ -379,7 +1131,7 @@ pub mod test_context_state_commands {
     let input = "".as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     context.context_state = ContextState::INGAME;
     context.commands = get_current_commands(&mut context);
     context.curr_game = Some(RotfGame::new("test me".to_owned(), RotfDifficulty::default()));
@@ -390,8 +1142,92 @@ pub mod test_context_state_commands {
     let error = std::str::from_utf8(&error).unwrap();
     assert!(output.contains("Player Info"));
     assert!(output.contains("Name: test me"));
+    assert!(output.contains("Time: Day 1 (DAWN)"));
+    assert_eq!(error, "");
+    run_cmd_output("delete test me --yes"); // clean up test
+  }
+
+  #[test]
+  fn test_inspect() {
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    let mut game = RotfGame::new("test inspect".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    context.context_state = ContextState::INGAME;
+    context.curr_game = Some(game);
+    context.commands = get_current_commands(&mut context);
+
+    run_cmd("inspect inv 1", &mut context);
+    run_cmd("inspect fish 1", &mut context);
+    run_cmd("examine", &mut context);
+
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    assert!(output.contains("Nothing found at that index. Use 'view' or 'inventory' to update indexes"));
+    assert!(output.contains("Can only inspect units, 'item' or 'inv'"));
+    assert!(output.contains("Invalid index. Must be a positive integer"));
+    assert_eq!(error, "");
+    run_cmd_output("delete test inspect --yes"); // clean up test
+  }
+
+  #[test]
+  fn test_talk() {
+    // food, an invalid reply, who, goodbye; then the legend, which plays a cutscene
+    let input = "2\n9\n1\n2\n3\n".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    let mut game = RotfGame::new("test talk".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.player.level = 3;
+    let mut npc = Npc::new("OLD_TOAD".to_owned(), "Old Toad".to_owned());
+    npc.set_position(Position::NEAR);
+    game.environment.add_npc(npc);
+    context.context_state = ContextState::INGAME;
+    context.curr_game = Some(game);
+    context.commands = get_current_commands(&mut context);
+
+    run_cmd("talk 1", &mut context);
+    run_cmd("view", &mut context);
+    run_cmd("talk 1", &mut context);
+    run_cmd("talk 1", &mut context);
+
+    assert_eq!(context.curr_game.as_ref().unwrap().state, GameState::ENVIRONMENT);
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    assert!(output.contains("NPC not found. Use 'view' to update view"));
+    assert!(output.contains("NPCs\n  1: Old Toad"));
+    assert!(output.contains("  3: Tell me about the legend.\n  4: Goodbye.\n  0: Leave"));
+    assert!(!output.contains("I found this fleck."));
+    assert!(output.contains("Old Toad:\n  Anything smaller than you"));
+    assert!(output.contains("Not an accepted reply"));
+    assert!(output.contains("Old Toad:\n  I was a tadpole once."));
+    assert!(output.contains("Chapter 1: A Legend is Laid"));
     assert_eq!(error, "");
-    run_cmd_output("delete test me"); // clean up test
+    run_cmd_output("delete test talk --yes"); // clean up test
+  }
+
+  #[test]
+  fn test_bestiary() {
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    context.context_state = ContextState::INGAME;
+    context.commands = get_current_commands(&mut context);
+    context.curr_game = Some(RotfGame::new("test bestiary".to_owned(), RotfDifficulty::default()));
+
+    run_cmd("bestiary", &mut context);
+    run_cmd("bestiary 0", &mut context);
+
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    assert!(output.contains("-- Bestiary --\nUnits\n  None encountered yet"));
+    assert!(output.contains("Invalid tier. Must be a positive integer"));
+    assert_eq!(error, "");
+    run_cmd_output("delete test bestiary --yes"); // clean up test
   }
 
   #[test]
@@ -406,7 +1242,7 @@ pub mod test_context_state_commands {
     let input = "".as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     context.context_state = ContextState::INGAME;
     context.commands = get_current_commands(&mut context);
     context.curr_game = Some(RotfGame::new("test save".to_owned(), RotfDifficulty::default()));
@@ -417,8 +1253,147 @@ pub mod test_context_state_commands {
     assert!(context.curr_game.is_none());
     let output = std::str::from_utf8(&output).unwrap();
     let error = std::str::from_utf8(&error).unwrap();
-    run_cmd_output("delete test save"); // clean up test
+    run_cmd_output("delete test save --yes"); // clean up test
     assert!(output.contains("Saved game"));
     assert_eq!(error, "");
   }
+
+  #[rstest]
+  #[case::every_command(AutosavePolicy::EVERY_COMMAND, "command", 2, 2)]
+  #[case::every_n_turns(AutosavePolicy::EVERY_N_TURNS, "turns", 1, 1)]
+  #[case::state_change(AutosavePolicy::STATE_CHANGE, "state", 0, 1)]
+  #[case::manual(AutosavePolicy::MANUAL, "manual", 0, 1)]
+  fn test_autosave(#[case] policy: AutosavePolicy, #[case] name: &str, #[case] saves: usize, #[case] saves_after_exit: usize) {
+    let slot = format!("test_autosave_{}", name);
+    let mut game = RotfGame::new(format!("test autosave {}", name), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.player.level = 10; // nothing spawns past tier 1, so only commands change the game
+    game.save(&test_paths()).unwrap();
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    context.options.autosave = policy;
+    context.options.autosave_turns = 2;
+
+    run_cmd(&format!("launch {}", slot), &mut context);
+    run_cmd("wait", &mut context);
+    run_cmd("wait", &mut context);
+    let generations = save_writer::generations(&test_paths(), &slot);
+    run_cmd("exit", &mut context);
+    let generations_after_exit = save_writer::generations(&test_paths(), &slot);
+
+    run_cmd_output(&format!("delete {} --yes", slot)); // clean up test
+    assert_eq!(generations.len(), saves);
+    assert_eq!(generations_after_exit.len(), saves_after_exit);
+  }
+
+  #[test]
+  fn test_autosave_counts_only_turns() {
+    let mut game = RotfGame::new("test autosave counted".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.player.level = 10; // nothing spawns past tier 1, so only commands change the game
+    game.save(&test_paths()).unwrap();
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    context.options.autosave = AutosavePolicy::EVERY_N_TURNS;
+    context.options.autosave_turns = 2;
+
+    run_cmd("launch test autosave counted", &mut context);
+    run_cmd("wait", &mut context);
+    run_cmd("help", &mut context);
+    run_cmd("ls", &mut context);
+    run_cmd("unknown", &mut context);
+    let turns = context.curr_game.as_ref().unwrap().turns_since_save;
+    let generations = save_writer::generations(&test_paths(), "test_autosave_counted");
+    run_cmd("wait", &mut context);
+    let generations_after_wait = save_writer::generations(&test_paths(), "test_autosave_counted");
+
+    run_cmd_output("delete test autosave counted --yes"); // clean up test
+    assert_eq!(turns, 1);
+    assert!(generations.is_empty());
+    assert_eq!(generations_after_wait.len(), 1);
+    assert_eq!(context.curr_game.unwrap().turns_since_save, 0);
+  }
+
+  #[test]
+  fn test_autosave_unchanged_game() {
+    let mut game = RotfGame::new("test autosave unchanged".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.player.level = 10; // nothing spawns past tier 1, so only commands change the game
+    game.save(&test_paths()).unwrap();
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+
+    run_cmd("launch test autosave unchanged", &mut context);
+    let generations = save_writer::generations(&test_paths(), "test_autosave_unchanged");
+    run_cmd("me", &mut context);
+    run_cmd("inventory", &mut context);
+    let generations_after_reading = save_writer::generations(&test_paths(), "test_autosave_unchanged");
+
+    run_cmd_output("delete test autosave unchanged --yes"); // clean up test
+    assert_eq!(generations, generations_after_reading);
+    assert!(!context.curr_game.unwrap().dirty);
+  }
+
+  #[test]
+  fn test_view_unchanged() {
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    context.options.autosave = AutosavePolicy::MANUAL;
+    let mut game = RotfGame::new("test view unchanged".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.player.level = 10; // nothing spawns past tier 1, so only commands change the game
+    let mut npc = Npc::new("OLD_TOAD".to_owned(), "Old Toad".to_owned());
+    npc.set_position(Position::NEAR);
+    game.environment.add_npc(npc);
+    context.context_state = ContextState::INGAME;
+    context.curr_game = Some(game);
+    context.commands = get_current_commands(&mut context);
+
+    run_cmd("view", &mut context);
+    let dirty = context.curr_game.as_ref().unwrap().dirty;
+    context.curr_game.as_mut().unwrap().dirty = false;
+    run_cmd("view", &mut context);
+    let dirty_after_repeat = context.curr_game.as_ref().unwrap().dirty;
+
+    run_cmd_output("delete test view unchanged --yes"); // clean up test
+    assert!(dirty);
+    assert!(!dirty_after_repeat);
+  }
+
+  #[test]
+  fn test_travel() {
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
+    let mut game = RotfGame::new("test travel".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    game.environment.add_npc(Npc::new("OLD_TOAD".to_owned(), "Old Toad".to_owned()));
+    context.context_state = ContextState::INGAME;
+    context.curr_game = Some(game);
+    context.commands = get_current_commands(&mut context);
+
+    run_cmd("travel desert", &mut context);
+    run_cmd("travel pond", &mut context);
+    run_cmd("travel forest_floor", &mut context);
+
+    let environment = &context.curr_game.as_ref().unwrap().environment;
+    assert_eq!(environment.biome, Biome::FOREST_FLOOR);
+    assert!(environment.npcs.is_empty());
+    let output = std::str::from_utf8(&output).unwrap();
+    let error = std::str::from_utf8(&error).unwrap();
+    assert!(output.contains("Unknown biome. Must be one of POND, STREAM, MARSH, FOREST_FLOOR, UNDERGROUND"));
+    assert!(output.contains("You are already in the POND"));
+    assert!(output.contains("You travel to the FOREST_FLOOR"));
+    assert_eq!(error, "");
+    run_cmd_output("delete test travel --yes"); // clean up test
+  }
 }
@@ -1,7 +1,13 @@
 use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use strum::IntoEnumIterator;
 
 use crate::context;
+use crate::dialogue::Dialogue;
 use crate::game::GameState;
+use crate::game::bestiary::Encounter;
+use crate::game::biome::Biome;
 use crate::game::traits::Positionable;
 use crate::game::environment::Position;
 
@@ -22,6 +28,9 @@ pub fn command<R, W, E>(context: &mut context::RotfContext<R, W, E>, cmd: &str)
           "pickup" => pickup(context),
           "inventory" => inventory(context),
           "drop" => drop(context),
+          "inspect" => inspect(context),
+          "talk" => talk(context),
+          "travel" => travel(context),
           _ => context.eprintln(format!("Environment command {} not implemented", cmd).as_str()),
         }
       }
@@ -43,13 +52,27 @@ fn view<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   E: Write,
 {
   let game = context.curr_game.as_mut().unwrap();
-  let mut output_str = String::new();
+  let mut output_str = format!("{}\n\n", game.environment.conditions());
+  let mut seen_units = Vec::new();
+  let mut seen_items = Vec::new();
+  // whether a view index, seen flag or announcement changed
+  let mut changed = false;
   let mut index = 1;
   for (_, unit) in game.environment.units.iter_mut() {
     if unit.despawn() || !game.player.can_view(unit) {
+      changed |= unit.view_index != -1;
       unit.view_index = -1;
       continue;
     }
+    if unit.mark_seen() {
+      seen_units.push(unit.id());
+      changed = true;
+    }
+    if unit.announce() {
+      changed = true;
+      output_str = format!("A rare elite {} has appeared!\n\n", context.unit_loader.get_data(unit.id()).name) + &output_str;
+    }
+    changed |= unit.view_index != index;
     unit.view_index = index;
     if index == 1 {
       output_str += "Units\n";
@@ -60,9 +83,15 @@ fn view<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   index = 1;
   for (_, item) in game.environment.items.iter_mut() {
     if !game.player.can_view(item) {
+      changed |= item.view_index != -1;
       item.view_index = -1;
       continue;
     }
+    if item.mark_seen() {
+      seen_items.push(item.id());
+      changed = true;
+    }
+    changed |= item.view_index != index;
     item.view_index = index;
     if index == 1 {
       output_str += "\nItems\n";
@@ -70,6 +99,31 @@ fn view<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
     output_str += &format!("  {}: {}\n", index, item.view_short(&context.item_loader));
     index += 1;
   }
+  index = 1;
+  for (_, npc) in game.environment.npcs.iter_mut() {
+    if !game.player.can_view(npc) {
+      changed |= npc.view_index != -1;
+      npc.view_index = -1;
+      continue;
+    }
+    changed |= npc.view_index != index;
+    npc.view_index = index;
+    if index == 1 {
+      output_str += "\nNPCs\n";
+    }
+    output_str += &format!("  {}: {}\n", index, npc.name);
+    index += 1;
+  }
+  for id in seen_units {
+    game.player.bestiary.record_unit(id, Encounter::SEEN, &game.environment.clock);
+  }
+  for id in seen_items {
+    game.player.bestiary.record_item(id, Encounter::SEEN, &game.environment.clock);
+  }
+  // view indexes are saved so later commands can refer to them
+  if changed {
+    game.dirty = true;
+  }
   context.println(output_str.as_str());
 }
 
@@ -81,6 +135,7 @@ fn wait<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   view(context);
   let game = context.curr_game.as_mut().unwrap();
   game.environment.pass_time();
+  game.dirty = true;
 }
 
 fn fight<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
@@ -109,6 +164,7 @@ fn fight<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
         context.println("Unit no longer in view. Use 'view' to update view");
         return;
       }
+      game.player.bestiary.record_unit(unit.id(), Encounter::FOUGHT, &game.environment.clock);
       game.environment.pass_time();
       game.enter_combat(unit_index, true);
     },
@@ -143,17 +199,20 @@ fn pickup<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
   match pickup_index {
     Some(i) => {
       let item = game.environment.items.remove(&i).unwrap();
+      game.dirty = true;
       if !game.player.can_view(&item) {
         context.println("Item no longer in view. Use 'view' to update view");
         return;
       }
       let item_string = item.to_string();
+      let item_id = item.id();
       match game.player.inventory.add(item) {
         Some(it) => {
           game.environment.add_item(it);
           context.println("Inventory full");
         },
         None => {
+          game.player.bestiary.record_item(item_id, Encounter::PICKED_UP, &game.environment.clock);
           game.environment.pass_time();
           context.println(&format!("Picked up {}", item_string));
         },
@@ -205,10 +264,106 @@ fn drop<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
       item.set_position(Position::NEAR);
       let item_string = item.view_short(&context.item_loader);
       game.environment.add_item(item);
+      game.dirty = true;
       context.println(&format!("Dropped {}", item_string));
     },
     None => {
       context.println("Item not found");
     },
   }
+}
+
+fn inspect<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let game = context.curr_game.as_ref().unwrap();
+  let params = context.last_params.clone();
+  let (target, index) = match params.split_once(" ") {
+    Some((target, index)) => (target, index.trim()),
+    None => ("", params.as_str()),
+  };
+  let index = index.parse::<i64>().unwrap_or(-1);
+  if index < 1 {
+    context.println("Invalid index. Must be a positive integer");
+    return;
+  }
+  let player_level = game.player.level;
+  let details = match target {
+    "" => game.environment.units.values()
+      .find(|unit| unit.view_index == index && !unit.despawn() && game.player.can_view(*unit))
+      .map(|unit| unit.inspect(&context.unit_loader, player_level)),
+    "item" => game.environment.items.values()
+      .find(|item| item.view_index == index && game.player.can_view(*item))
+      .map(|item| item.inspect(&context.item_loader, player_level)),
+    "inv" | "inventory" => game.player.inventory.get(index)
+      .map(|item| item.inspect(&context.item_loader, player_level)),
+    _ => {
+      context.println("Can only inspect units, 'item' or 'inv'. Use 'help inspect' for more information");
+      return;
+    },
+  };
+  match details {
+    Some(details) => context.println(&details),
+    None => context.println("Nothing found at that index. Use 'view' or 'inventory' to update indexes"),
+  }
+}
+
+fn talk<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let index = context.last_params.parse::<i64>().unwrap_or(-1);
+  if index < 1 {
+    context.println("Invalid index. Must be a positive integer");
+    return;
+  }
+  let game = context.curr_game.as_mut().unwrap();
+  let dialogue_id = game.environment.npcs.values()
+    .find(|npc| npc.view_index == index && game.player.can_view(*npc))
+    .map(|npc| npc.dialogue().to_owned());
+  let dialogue_id = match dialogue_id {
+    Some(id) => id,
+    None => {
+      context.println("NPC not found. Use 'view' to update view");
+      return;
+    },
+  };
+  game.environment.pass_time();
+  game.dirty = true;
+  // dialogue is read each time, like cutscenes, so it can be changed while playing
+  match Dialogue::load(&context.paths, &dialogue_id) {
+    Ok(dialogue) => match dialogue.talk(context) {
+      Ok(()) => {},
+      Err(e) => context.print_error("reading input", &e),
+    },
+    Err(e) => context.print_error("loading NPC dialogue", &e),
+  }
+}
+
+fn travel<R, W, E>(context: &mut context::RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let biome = match Biome::from_str(&context.last_params.to_uppercase()) {
+    Ok(biome) => biome,
+    Err(_) => {
+      let biomes: Vec<String> = Biome::iter().map(|biome| biome.to_string()).collect();
+      context.println(&format!("Unknown biome. Must be one of {}", biomes.join(", ")));
+      return;
+    },
+  };
+  if context.curr_game.as_ref().unwrap().environment.biome == biome {
+    context.println(&format!("You are already in the {}", biome));
+    return;
+  }
+  let dialogues = context.dialogues();
+  let game = context.curr_game.as_mut().unwrap();
+  game.environment.travel(biome.clone(), &game.player, &context.unit_loader, &context.item_loader);
+  game.dirty = true;
+  game.spawn_npcs(&dialogues);
+  context.println(&format!("You travel to the {}", biome));
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
 use std::io::{Write, BufRead};
+use std::str::FromStr;
 
-use crate::context::RotfContext;
+use crate::context::{RotfContext, validation};
 use crate::commands::Command;
 use crate::cutscene::RotfCutscene;
+use crate::game::{save_backend::SaveFormat, trash};
+use crate::options::AutosavePolicy;
 
 pub fn ls<R, W, E>(context: &mut RotfContext<R, W, E>) where
   R: BufRead,
@@ -96,6 +99,10 @@ pub fn options<R, W, E>(context: &mut RotfContext<R, W, E>) where
   loop {
     context.println("");
     context.println(format!("1: sleep_factor, value: {}", context.options.sleep_factor).as_str());
+    context.println(format!("2: save_format, value: {}", context.options.save_format).as_str());
+    context.println(format!("3: trash_size, value: {}", context.options.trash_size).as_str());
+    context.println(format!("4: autosave, value: {}", context.options.autosave).as_str());
+    context.println(format!("5: autosave_turns, value: {}", context.options.autosave_turns).as_str());
     context.println("");
     context.print(" choose an option > ");
     match context.read_line() {
@@ -116,13 +123,100 @@ pub fn options<R, W, E>(context: &mut RotfContext<R, W, E>) where
                 }
                 else {
                   context.options.sleep_factor = new_val;
-                  context.options.save();
+                  context.options.save(&context.paths);
                   context.println(format!("Changed sleep factor to {}", new_val).as_str());
                 }
               }
               Err(e) => context.print_error("reading input", &e),
             }
           }
+          "2" => {
+            context.println("save format");
+            context.println(format!("  current value: {}", context.options.save_format).as_str());
+            context.println("  accepted values: ROTF, JSON");
+            context.println("  only affects new and imported games; existing games keep their format");
+            context.println("");
+            context.print(" enter new value > ");
+            match context.read_line() {
+              Ok(v) => {
+                match SaveFormat::from_str(v.trim()) {
+                  Ok(format) => {
+                    context.println(format!("Changed save format to {}", format).as_str());
+                    context.options.save_format = format;
+                    context.options.save(&context.paths);
+                  },
+                  Err(_) => context.println("Not an accepted value for save format"),
+                }
+              }
+              Err(e) => context.print_error("reading input", &e),
+            }
+          }
+          "3" => {
+            context.println("trash size");
+            context.println(format!("  current value: {}", context.options.trash_size).as_str());
+            context.println("  accepted values: 0-100");
+            context.println("  the number of deleted saved games kept before the oldest is removed permanently");
+            context.println("");
+            context.print(" enter new value > ");
+            match context.read_line() {
+              Ok(v) => {
+                match v.trim().parse::<usize>() {
+                  Ok(new_val) if new_val <= 100 => {
+                    context.options.trash_size = new_val;
+                    context.options.save(&context.paths);
+                    context.println(format!("Changed trash size to {}", new_val).as_str());
+                    trash::purge(&context.paths, new_val).unwrap_or_else(|e| {
+                      context.print_error("emptying trash", &e);
+                    });
+                  },
+                  _ => context.println("Not an accepted value for trash size"),
+                }
+              }
+              Err(e) => context.print_error("reading input", &e),
+            }
+          }
+          "4" => {
+            context.println("autosave");
+            context.println(format!("  current value: {}", context.options.autosave).as_str());
+            context.println("  accepted values: EVERY_COMMAND, EVERY_N_TURNS, STATE_CHANGE, MANUAL");
+            context.println("  when a game in progress is saved without using 'save'; games are always saved on exit");
+            context.println("");
+            context.print(" enter new value > ");
+            match context.read_line() {
+              Ok(v) => {
+                match AutosavePolicy::from_str(v.trim()) {
+                  Ok(policy) => {
+                    context.println(format!("Changed autosave to {}", policy).as_str());
+                    context.options.autosave = policy;
+                    context.options.save(&context.paths);
+                  },
+                  Err(_) => context.println("Not an accepted value for autosave"),
+                }
+              }
+              Err(e) => context.print_error("reading input", &e),
+            }
+          }
+          "5" => {
+            context.println("autosave turns");
+            context.println(format!("  current value: {}", context.options.autosave_turns).as_str());
+            context.println("  accepted values: 1-100");
+            context.println("  the number of in-game commands between autosaves when autosave is EVERY_N_TURNS");
+            context.println("");
+            context.print(" enter new value > ");
+            match context.read_line() {
+              Ok(v) => {
+                match v.trim().parse::<usize>() {
+                  Ok(new_val) if new_val >= 1 && new_val <= 100 => {
+                    context.options.autosave_turns = new_val;
+                    context.options.save(&context.paths);
+                    context.println(format!("Changed autosave turns to {}", new_val).as_str());
+                  },
+                  _ => context.println("Not an accepted value for autosave turns"),
+                }
+              }
+              Err(e) => context.print_error("reading input", &e),
+            }
+          }
           _ => context.println("Invalid input. If you wish to leave the option menu, enter '0'"),
         }
       },
@@ -132,9 +226,60 @@ pub fn options<R, W, E>(context: &mut RotfContext<R, W, E>) where
 }
 
 
+pub fn validate<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let issues = validation::validate(&context.paths);
+  context.println(&validation::report(&issues));
+}
+
+pub fn reload<R, W, E>(context: &mut RotfContext<R, W, E>) where
+  R: BufRead,
+  W: Write,
+  E: Write,
+{
+  let issues = validation::validate(&context.paths);
+  if validation::has_errors(&issues) {
+    context.println(&validation::report(&issues));
+    context.println("Kept the loaded data; fix the errors and reload again");
+    return;
+  }
+  if !issues.is_empty() {
+    context.println(&validation::report(&issues));
+  }
+  // data that was never loaded is loaded when a game launches
+  if context.unit_loader.data_loaded() {
+    match context.unit_loader.reload(&context.paths) {
+      Ok(changes) => context.println(&changes.summary("unit")),
+      Err(e) => context.print_error("reloading unit data", &e),
+    }
+  }
+  if context.item_loader.data_loaded() {
+    match context.item_loader.reload(&context.paths) {
+      Ok(changes) => context.println(&changes.summary("item")),
+      Err(e) => context.print_error("reloading item data", &e),
+    }
+  }
+  let game = match &mut context.curr_game {
+    Some(game) => game,
+    None => return,
+  };
+  context.unit_loader.update_current(&game.player);
+  context.item_loader.update_current(&game.player);
+  let (missing_units, missing_items) = game.missing_data(&context.unit_loader, &context.item_loader);
+  if missing_units + missing_items > 0 {
+    context.println(&format!("{} units and {} items in the game no longer have data and show as unknown",
+      missing_units, missing_items));
+  }
+}
+
 #[cfg(test)]
 pub mod test_system_commands {
+  use std::str;
   use rstest::*;
+  use crate::filesystem;
   use crate::test_main::*;
   use crate::context::RotfContext;
   use crate::game::{RotfGame, RotfDifficulty};
@@ -183,6 +328,46 @@ pub mod test_system_commands {
     assert_eq!(context.exit, true);
   }
 
+  #[test]
+  fn test_validate() {
+    let (output, error) = run_cmd_output("validate");
+    assert!(output.contains("WARNING: data/items/data.csv row 5: item id 2 has no name"));
+    assert!(output.contains("Found 0 errors and"));
+    assert_eq!(error, "");
+  }
+
+  #[test]
+  fn test_reload() {
+    let temp = TempRoot::new("reload");
+    let paths = &temp.paths;
+    for folder in ["units", "items", "cutscenes"] {
+      filesystem::copy_folder(format!("data/{}", folder), format!("{}/{}", paths.data_root, folder)).unwrap();
+    }
+    let units = filesystem::read_file(paths.units_file()).unwrap();
+    let input = "".as_bytes();
+    let mut output = Vec::new();
+    let mut error = Vec::new();
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, paths.clone());
+    context.unit_loader.load_data(paths).unwrap();
+    context.item_loader.load_data(paths).unwrap();
+    // rename unit 4, unname unit 3 and add unit 99
+    filesystem::create_file(paths.units_file(), units
+      .replace("Drowning Grub   ", "Drowning Beetle ")
+      .replace("Drowning Waxworm", "                ")
+      .replace("     ,  5 ,", "     , 99 ,Pond Snail ,0-3 ,,,,5\n     ,  5 ,")).unwrap();
+    run_cmd("reload", &mut context);
+    assert!(context.unit_loader.contains(99));
+    // data with errors isn't loaded
+    filesystem::create_file(paths.units_file(), units.replace("  5 ,", "  1 ,")).unwrap();
+    run_cmd("reload", &mut context);
+    assert!(context.unit_loader.contains(99));
+    let output = str::from_utf8(&output).unwrap();
+    assert!(output.contains("unit data: added 99; changed 4; removed 3\nitem data: no changes\n"));
+    assert!(output.contains("unit id 1 is already used on row 4"));
+    assert!(output.contains("Kept the loaded data; fix the errors and reload again"));
+    assert_eq!(str::from_utf8(&error).unwrap(), "");
+  }
+
   #[test]
   fn test_credits() {
     let (output, error) = run_cmd_output("credits");
@@ -198,7 +383,7 @@ pub mod test_system_commands {
     let input = "".as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     let game = RotfGame::new(format!("test replay {}", cmd), RotfDifficulty::default());
     context.curr_game = Some(game);
 
@@ -206,7 +391,7 @@ pub mod test_system_commands {
 
     let output = std::str::from_utf8(&output).unwrap();
     let error = std::str::from_utf8(&error).unwrap();
-    run_cmd_output(format!("delete test replay {}", cmd).as_str()); // clean up test
+    run_cmd_output(format!("delete test replay {} --yes", cmd).as_str()); // clean up test
     assert!(output.contains(expected));
     assert_eq!(error, "");
   }
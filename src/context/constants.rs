@@ -9,4 +9,35 @@ pub const ITEM_SPAWN_RANGE_MIN: u8 = 10;
 // Max level of auto-spawned items
 pub const ITEM_SPAWN_RANGE_MAX: u8 = 3;
 // Chance a item will move in a given unit of time
-pub const ITEM_MOVE_CHANCE: f64 = 0.05;
\ No newline at end of file
+pub const ITEM_MOVE_CHANCE: f64 = 0.05;
+
+// Number of ticks in a game day (one tick is an hour)
+pub const TICKS_PER_DAY: u64 = 24;
+// Tick a new game clock starts at (dawn of the first day)
+pub const CLOCK_START_TICK: u64 = 6;
+// Factor applied to the move chance of units outside their active phase
+pub const INACTIVE_MOVE_FACTOR: f64 = 0.2;
+
+// Number of days in a season
+pub const DAYS_PER_SEASON: u64 = 7;
+
+// Spawn weight of units and items with no rarity specified
+pub const DEFAULT_SPAWN_WEIGHT: u32 = 10;
+// Chance a spawned unit is elite
+pub const ELITE_SPAWN_CHANCE: f64 = 0.05;
+// Levels added to an elite unit
+pub const ELITE_LEVEL_BONUS: u8 = 2;
+// Factor applied to the stats of an elite unit
+pub const ELITE_STAT_FACTOR: f64 = 1.5;
+// Levels above the player at which inspect still shows a unit's or item's stats
+pub const INSPECT_STATS_LEVELS: u8 = 3;
+
+// Version of the save format written by this binary
+pub const SAVE_VERSION: u32 = 3;
+
+// Number of previous generations of a save kept as backups
+pub const SAVE_BACKUP_GENERATIONS: usize = 5;
+// Number of deleted saves kept in the trash by default
+pub const DEFAULT_TRASH_SIZE: usize = 5;
+// Number of in-game commands between autosaves when autosaving every few turns
+pub const DEFAULT_AUTOSAVE_TURNS: usize = 5;
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::filesystem;
+use crate::numeric::IntegerRange;
+use crate::paths::RotfPaths;
+use crate::rotf_file::{self, RotfEntry};
+
+
+// Extra units, items and cutscenes in data/mods/<name>/, laid out like the base data
+#[derive(Debug, PartialEq)]
+pub struct ContentPack {
+  pub name: String,
+  pub ids: IntegerRange, // unit and item ids reserved for the pack
+}
+
+impl ContentPack {
+  // Reads every pack's mod.rotf, in order of the pack folder names; reserved ids can't overlap
+  pub fn installed(paths: &RotfPaths) -> Result<Vec<ContentPack>, Error> {
+    let mut packs: Vec<ContentPack> = Vec::new();
+    if !Path::new(&paths.mods()).is_dir() {
+      return Ok(packs);
+    }
+    for entry in filesystem::open_folder(paths.mods())? {
+      if !entry.is_dir() {
+        continue;
+      }
+      let name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+      let pack = ContentPack::load(paths, &name)?;
+      match packs.iter().find(|p| p.ids.overlaps(&pack.ids)) {
+        Some(p) => return Err(Error::new(ErrorKind::InvalidData, format!(
+          "{}: ids {} overlap the ids {} reserved by content pack '{}'",
+          paths.content_pack_file(&name), pack.ids, p.ids, p.name))),
+        None => packs.push(pack),
+      }
+    }
+    return Ok(packs);
+  }
+
+  pub fn installed_names(paths: &RotfPaths) -> Result<Vec<String>, Error> {
+    return Ok(ContentPack::installed(paths)?.into_iter().map(|pack| pack.name).collect());
+  }
+
+  fn load(paths: &RotfPaths, name: &str) -> Result<ContentPack, Error> {
+    let file = paths.content_pack_file(name);
+    let mut ids = None;
+    for entry in rotf_file::parse_file(file.clone())? {
+      match entry {
+        RotfEntry::Block(block) => return Err(block.unknown_kind().into()),
+        RotfEntry::Line(line) => match line.key.as_str() {
+          "ids" => match IntegerRange::parse(&line.value) {
+            Ok(range) if range.min() > 0 => ids = Some(range),
+            _ => return Err(line.error("a range of ids above 0 like 1000-1999").into()),
+          },
+          _ => return Err(line.unknown_key().into()),
+        },
+      }
+    }
+    match ids {
+      Some(ids) => Ok(ContentPack {
+        name: name.to_owned(),
+        ids,
+      }),
+      None => Err(Error::new(ErrorKind::InvalidData, format!("{}: missing 'ids' line reserving the pack's ids", file))),
+    }
+  }
+
+  // Paths to the pack's data files
+  pub fn paths(&self, paths: &RotfPaths) -> RotfPaths {
+    return paths.content_pack(&self.name);
+  }
+
+  // The last pack with the file replaces the base cutscene
+  pub fn cutscene_file(paths: &RotfPaths, cutscene: &str) -> String {
+    return ContentPack::last_file(paths, |p| p.cutscene_file(cutscene));
+  }
+
+  // The last pack with the file replaces the base NPC dialogue
+  pub fn npc_file(paths: &RotfPaths, npc: &str) -> String {
+    return ContentPack::last_file(paths, |p| p.npc_file(npc));
+  }
+
+  fn last_file<F: Fn(&RotfPaths) -> String>(paths: &RotfPaths, file: F) -> String {
+    let packs = ContentPack::installed(paths).unwrap_or_default();
+    for pack in packs.iter().rev() {
+      let pack_file = file(&pack.paths(paths));
+      if Path::new(&pack_file).exists() {
+        return pack_file;
+      }
+    }
+    return file(paths);
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_content_pack {
+  use crate::filesystem;
+  use crate::context::content_pack::*;
+  use crate::context::unit_loader::UnitLoader;
+  use crate::test_main::TempRoot;
+
+  #[test]
+  fn test_installed() {
+    let temp = TempRoot::new("packs");
+    let (root, paths) = (temp.root(), &temp.paths);
+    assert_eq!(ContentPack::installed(paths).unwrap(), vec![]);
+    for (name, ids) in [("b_pack", "2000-2999"), ("a_pack", "1000-1999")] {
+      filesystem::create_folder(format!("{}/cutscenes", paths.content_pack(name).data_root)).unwrap();
+      filesystem::create_file(paths.content_pack_file(name), format!("ids: {}", ids)).unwrap();
+    }
+    filesystem::create_file(paths.content_pack("a_pack").cutscene_file("LAUNCH_GAME"), "".to_owned()).unwrap();
+    assert_eq!(ContentPack::installed_names(paths).unwrap(), vec!["a_pack", "b_pack"]);
+    assert_eq!(ContentPack::cutscene_file(paths, "LAUNCH_GAME"), format!("{}/mods/a_pack/cutscenes/LAUNCH_GAME.rotf", root));
+    assert_eq!(ContentPack::cutscene_file(paths, "OTHER"), format!("{}/cutscenes/OTHER.rotf", root));
+    // overlapping and missing reservations
+    filesystem::create_file(paths.content_pack_file("b_pack"), "ids: 1500-2500".to_owned()).unwrap();
+    assert_eq!(ContentPack::installed(paths).err().unwrap().to_string(),
+      format!("{}/mods/b_pack/mod.rotf: ids 1500-2500 overlap the ids 1000-1999 reserved by content pack 'a_pack'", root));
+    filesystem::create_file(paths.content_pack_file("b_pack"), "".to_owned()).unwrap();
+    assert_eq!(ContentPack::installed(paths).err().unwrap().to_string(),
+      format!("{}/mods/b_pack/mod.rotf: missing 'ids' line reserving the pack's ids", root));
+  }
+
+  #[test]
+  fn test_load_pack_data() {
+    let temp = TempRoot::new("pack_data");
+    let (root, paths) = (temp.root(), &temp.paths);
+    let pack = paths.content_pack("extra");
+    filesystem::create_folder(format!("{}/units", root)).unwrap();
+    filesystem::create_folder(format!("{}/units", pack.data_root)).unwrap();
+    filesystem::create_file(paths.units_file(), "ID ,Name ,Level Range\n1 ,Frog ,0-3\n".to_owned()).unwrap();
+    filesystem::create_file(paths.content_pack_file("extra"), "ids: 100-199".to_owned()).unwrap();
+    filesystem::create_file(pack.units_file(), "ID ,Name ,Level Range\n100 ,Newt ,0-3\n".to_owned()).unwrap();
+    let mut loader = UnitLoader::new();
+    loader.load_data(paths).unwrap();
+    assert_eq!(loader.get_data(100).name, "Newt");
+    // ids outside the reservation aren't loaded
+    filesystem::create_file(pack.units_file(), "ID ,Name ,Level Range\n1 ,Toad ,0-3\n".to_owned()).unwrap();
+    let e = UnitLoader::new().load_data(paths).err().unwrap();
+    assert_eq!(e.to_string(), format!("{} row 2: column 'ID' expected an id in the pack's reserved ids 100-199, found '1'",
+      pack.units_file()));
+  }
+}
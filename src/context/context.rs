@@ -1,6 +1,8 @@
 use crate::commands;
 use crate::game::RotfGame;
+use crate::dialogue::Dialogue;
 use crate::options::{self, RotfOptions};
+use crate::paths::RotfPaths;
 
 use std::collections::HashMap;
 use std::error::Error;
@@ -9,12 +11,17 @@ use std::io::{BufRead, Write, self};
 use std::thread;
 use std::time::Duration;
 
+use self::content_pack::ContentPack;
 use self::unit_loader::UnitLoader;
 use self::item_loader::ItemLoader;
 
+pub mod content_pack;
+pub mod data_loader;
+pub mod generator;
 pub mod unit_loader;
 pub mod item_loader;
 pub mod constants;
+pub mod validation;
 
 
 // Overall program state
@@ -37,12 +44,14 @@ pub struct RotfContext<R, W, E> where
   testing: bool,
   pub exit: bool,
 
+  pub paths: RotfPaths,
   pub options: options::RotfOptions,
   pub context_state: ContextState,
   pub all_commands: HashMap<String, commands::Command>,
   pub commands: HashMap<String, commands::Command>,
   pub last_cmd: String,
   pub last_params: String,
+  pub last_raw_params: String, // last_params with case preserved, used for names
 
   pub curr_game: Option<RotfGame>,
   pub unit_loader: UnitLoader,
@@ -153,20 +162,22 @@ impl<R, W, E> RotfContext<R, W, E> where
     thread::sleep(Duration::from_millis(amount));
   }
 
-  pub fn default(input: R, output: W, error: E) -> RotfContext<R, W, E> {
+  pub fn default(input: R, output: W, error: E, paths: RotfPaths) -> RotfContext<R, W, E> {
     let mut context: RotfContext<R, W, E> = RotfContext {
       input,
       output,
       error,
       testing: true, // have to manually override
       exit: false,
-      options: RotfOptions::default(),
+      options: RotfOptions::default(&paths),
+      paths,
       
       context_state: ContextState::HOME,
       all_commands: commands::get_all_commands(),
       commands: HashMap::new(),
       last_cmd: "".to_owned(),
       last_params: "".to_owned(),
+      last_raw_params: "".to_owned(),
   
       curr_game: None,
       unit_loader: UnitLoader::new(), // empty loader
@@ -176,13 +187,29 @@ impl<R, W, E> RotfContext<R, W, E> where
     return context;
   }
 
-  pub fn default_context(input: R, output: W, error: E, testing: bool) -> RotfContext<R, W, E> {
-    let mut context = RotfContext::default(input, output, error);
+  pub fn default_context(input: R, output: W, error: E, testing: bool, paths: RotfPaths) -> RotfContext<R, W, E> {
+    let mut context = RotfContext::default(input, output, error, paths);
     context.testing = testing;
     context.commands = commands::get_current_commands(&mut context);
     return context;
   }
 
+  // NPC dialogues that load; the others are reported and their NPCs left out of the game
+  pub fn dialogues(&mut self) -> Vec<Dialogue> {
+    match Dialogue::installed(&self.paths) {
+      Ok((dialogues, errors)) => {
+        for e in errors {
+          self.print_error("loading NPC dialogue", &e);
+        }
+        return dialogues;
+      },
+      Err(e) => {
+        self.print_error("loading NPC dialogue", &e);
+        return Vec::new();
+      },
+    }
+  }
+
   pub fn launch_game(&mut self, mut game: RotfGame, new: bool) {
     if new {
       self.println("Launching new game ...\n");
@@ -191,27 +218,38 @@ impl<R, W, E> RotfContext<R, W, E> where
       self.println("Launching game ...\n");
     }
     // load unit data
-    match self.unit_loader.load_data() {
+    match self.unit_loader.load_data(&self.paths) {
       Ok(()) => {},
       Err(e) => {
         self.print_error("loading unit data", &e);
         return;
       },
     }
-    self.unit_loader.update_current_units(&game.player);
+    self.unit_loader.update_current(&game.player);
     // load item data
-    match self.item_loader.load_data() {
+    match self.item_loader.load_data(&self.paths) {
       Ok(()) => {},
       Err(e) => {
         self.print_error("loading item data", &e);
         return;
       },
     }
-    self.item_loader.update_current_items(&game.player);
+    self.item_loader.update_current(&game.player);
+    // record the content packs the game uses
+    match ContentPack::installed(&self.paths) {
+      Ok(packs) => game.set_content_packs(game.content_packs_used(&packs)),
+      Err(e) => {
+        self.print_error("loading content packs", &e);
+        return;
+      },
+    }
     // initial spawns
     if new {
       game.initial_spawns(&self.unit_loader, &self.item_loader);
     }
+    // npcs, which also spawn into games saved before their dialogue was added
+    let dialogues = self.dialogues();
+    game.spawn_npcs(&dialogues);
     // launch game
     self.curr_game = Some(game);
     self.context_state = ContextState::INGAME;
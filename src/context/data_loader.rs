@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Error;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use strum::IntoEnumIterator;
+
+use crate::csv::{self, CsvRow, CsvParseError};
+use crate::game::biome::Biome;
+use crate::game::player::RotfPlayer;
+use crate::numeric::IntegerRange;
+use crate::paths::RotfPaths;
+
+use super::constants;
+use super::content_pack::ContentPack;
+use super::generator;
+
+
+// A kind of data loaded from a .csv file, like units or items
+pub trait DataRow: PartialEq {
+  // Name of the kind of data used in messages and references, like "unit"
+  const KIND: &'static str;
+  // Columns the header row must contain
+  const REQUIRED_COLUMNS: &'static [&'static str];
+
+  fn file(paths: &RotfPaths) -> String;
+  // Optional file of template rows that generate more data
+  fn templates_file(paths: &RotfPaths) -> String;
+  // Data returned for ids that aren't loaded
+  fn error_data() -> Self;
+  // Reads a row, returning None for spacer and reserved rows; the Tier column is already carried forward
+  fn from_row(row: &CsvRow) -> Result<Option<Self>, CsvParseError> where Self: Sized;
+
+  fn id(&self) -> u64;
+  fn name(&self) -> &str;
+  fn tier(&self) -> u8;
+  fn level_range(&self) -> &IntegerRange;
+  fn biomes(&self) -> &Vec<Biome>;
+  fn rarity(&self) -> u32; // spawn weight; lower is rarer
+  // Level a spawn of this data has when the player is the given level
+  fn spawn_level(&self, player_level: u8) -> u8;
+
+  // Kinds and ids of data in other files that this data refers to
+  fn references(&self) -> Vec<(&'static str, u64)> {
+    return vec![];
+  }
+
+  fn description(&self) -> &str;
+  fn flavor(&self) -> &str;
+  fn stats(&self) -> &DataStats;
+
+  // Details shown by inspect with stats scaled by the factor; stronger things reveal less to a lower level player
+  fn inspect(&self, level: u8, player_level: u8, stat_factor: f64) -> String {
+    let mut details = String::new();
+    if !self.description().is_empty() {
+      details += &format!("\n  {}", self.description());
+    }
+    if level <= player_level.saturating_add(constants::INSPECT_STATS_LEVELS) {
+      let stats = self.stats().scaled(stat_factor).to_string();
+      if !stats.is_empty() {
+        details += &format!("\n  {}", stats);
+      }
+    }
+    else {
+      details += &format!("\n  Stats unknown until you are level {}", level - constants::INSPECT_STATS_LEVELS);
+    }
+    if level <= player_level && !self.flavor().is_empty() {
+      details += &format!("\n  \"{}\"", self.flavor());
+    }
+    return details;
+  }
+}
+
+
+// Stats from the optional stat columns, where an empty column is 0
+#[derive(Debug, PartialEq)]
+pub struct DataStats {
+  pub health: u32,
+  pub attack: u32,
+  pub magic: u32,
+  pub defense: u32,
+  pub resistance: u32,
+}
+
+impl fmt::Display for DataStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let stats = [("Health", self.health), ("Attack", self.attack), ("Magic", self.magic),
+      ("Defense", self.defense), ("Resistance", self.resistance)];
+    // only the stats it has
+    write!(f, "{}", stats.iter().filter(|(_, value)| *value > 0)
+      .map(|(name, value)| format!("{}: {}", name, value)).collect::<Vec<String>>().join(", "))
+  }
+}
+
+impl DataStats {
+  pub fn new() -> DataStats {
+    return DataStats {
+      health: 0,
+      attack: 0,
+      magic: 0,
+      defense: 0,
+      resistance: 0,
+    }
+  }
+
+  pub fn from_row(row: &CsvRow) -> Result<DataStats, CsvParseError> {
+    return Ok(DataStats {
+      health: row.parse_or::<u32>("Health", 0, "an unsigned integer")?,
+      attack: row.parse_or::<u32>("Attack", 0, "an unsigned integer")?,
+      magic: row.parse_or::<u32>("Magic", 0, "an unsigned integer")?,
+      defense: row.parse_or::<u32>("Defense", 0, "an unsigned integer")?,
+      resistance: row.parse_or::<u32>("Resistance", 0, "an unsigned integer")?,
+    });
+  }
+
+  // Stats multiplied by the factor and rounded
+  pub fn scaled(&self, factor: f64) -> DataStats {
+    let scale = |stat: u32| (stat as f64 * factor).round() as u32;
+    return DataStats {
+      health: scale(self.health),
+      attack: scale(self.attack),
+      magic: scale(self.magic),
+      defense: scale(self.defense),
+      resistance: scale(self.resistance),
+    };
+  }
+}
+
+
+// Tier of a row, where rows before the first tier are tier 1
+pub fn parse_tier(s: &str) -> Result<u8, ()> {
+  if s.is_empty() {
+    return Ok(1);
+  }
+  match s.parse::<u8>() {
+    Ok(tier) if tier > 0 => Ok(tier),
+    _ => Err(()),
+  }
+}
+
+
+// Ids that differ between the loaded data and the data read again from file
+pub struct DataChanges {
+  pub added: Vec<u64>,
+  pub changed: Vec<u64>,
+  pub removed: Vec<u64>,
+}
+
+impl DataChanges {
+  pub fn is_empty(&self) -> bool {
+    return self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty();
+  }
+
+  // Line like "unit data: added 12, 13; changed 4"
+  pub fn summary(&self, kind: &str) -> String {
+    if self.is_empty() {
+      return format!("{} data: no changes", kind);
+    }
+    let mut parts = Vec::new();
+    for (name, ids) in [("added", &self.added), ("changed", &self.changed), ("removed", &self.removed)] {
+      if !ids.is_empty() {
+        parts.push(format!("{} {}", name, ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(", ")));
+      }
+    }
+    return format!("{} data: {}", kind, parts.join("; "));
+  }
+}
+
+
+// Service struct that parses a kind of data and delivers it to context
+pub struct DataLoader<T: DataRow> {
+  data: HashMap<u64, T>, // all rows
+  error_data: T,
+  current: HashMap<(Biome, u8), Vec<u64>>, // spawnable ids keyed by biome and level
+  current_level: u8,
+  data_loaded: bool,
+}
+
+impl<T: DataRow> DataLoader<T> {
+  pub fn new() -> DataLoader<T> {
+    return DataLoader {
+      data: HashMap::new(),
+      error_data: T::error_data(),
+      current: HashMap::new(),
+      current_level: 0,
+      data_loaded: false,
+    }
+  }
+
+  pub fn load_data(&mut self, paths: &RotfPaths) -> Result<(), Error> {
+    if self.data_loaded {
+      return Ok(())
+    }
+    self.data = Self::read_data(paths)?;
+    self.data_loaded = true;
+    Ok(())
+  }
+
+  // Reads the data file again, keeping the loaded data if it fails to parse; spawn pools are
+  // cleared so call update_current after
+  pub fn reload(&mut self, paths: &RotfPaths) -> Result<DataChanges, Error> {
+    let data = Self::read_data(paths)?;
+    let mut changes = DataChanges {
+      added: Vec::new(),
+      changed: Vec::new(),
+      removed: Vec::new(),
+    };
+    for (id, new_data) in &data {
+      match self.data.get(id) {
+        Some(old_data) if old_data != new_data => changes.changed.push(*id),
+        Some(_) => {},
+        None => changes.added.push(*id),
+      }
+    }
+    changes.removed = self.data.keys().filter(|id| !data.contains_key(id)).cloned().collect();
+    changes.added.sort();
+    changes.changed.sort();
+    changes.removed.sort();
+    self.data = data;
+    self.current.clear();
+    self.data_loaded = true;
+    return Ok(changes);
+  }
+
+  // Reads the base data followed by each content pack's data, which must use the pack's reserved ids
+  fn read_data(paths: &RotfPaths) -> Result<HashMap<u64, T>, Error> {
+    let mut data = HashMap::new();
+    Self::read_source(paths, None, &mut data)?;
+    for pack in ContentPack::installed(paths)? {
+      Self::read_source(&pack.paths(paths), Some(&pack), &mut data)?;
+    }
+    return Ok(data);
+  }
+
+  // Reads a data file then generates data from its templates file; a content pack needs neither file
+  fn read_source(paths: &RotfPaths, pack: Option<&ContentPack>, data: &mut HashMap<u64, T>) -> Result<(), Error> {
+    if pack.is_none() || Path::new(&T::file(paths)).exists() {
+      let mut rows = csv::parse_file(T::file(paths), T::REQUIRED_COLUMNS)?;
+      csv::carry_forward(&mut rows, "Tier");
+      Self::read_rows(rows, pack, data)?;
+    }
+    if Path::new(&T::templates_file(paths)).exists() {
+      Self::read_rows(generator::generate(T::templates_file(paths))?, pack, data)?;
+    }
+    Ok(())
+  }
+
+  fn read_rows(rows: Vec<CsvRow>, pack: Option<&ContentPack>, data: &mut HashMap<u64, T>) -> Result<(), Error> {
+    for row in rows {
+      let row_data = match T::from_row(&row)? {
+        Some(row_data) => row_data,
+        None => continue,
+      };
+      match pack {
+        Some(pack) if !pack.ids.contains(row_data.id() as i64) => {
+          return Err(row.error("ID", &format!("an id in the pack's reserved ids {}", pack.ids)).into());
+        },
+        _ => {},
+      }
+      if data.contains_key(&row_data.id()) {
+        return Err(row.error("ID", "an id not used by an earlier row").into());
+      }
+      data.insert(row_data.id(), row_data);
+    }
+    Ok(())
+  }
+
+  // Builds the spawn pools for the player's level and tier
+  pub fn update_current(&mut self, player: &RotfPlayer) {
+    self.current_level = player.level;
+    for biome in Biome::iter() {
+      let key = (biome.clone(), self.current_level);
+      if self.current.contains_key(&key) {
+        continue;
+      }
+      let mut ids = Vec::new();
+      for (id, data) in &self.data {
+        if data.tier() == player.tier() && data.level_range().contains(self.current_level.into())
+          && data.biomes().contains(&biome) {
+          ids.push(*id);
+        }
+      }
+      self.current.insert(key, ids);
+    }
+  }
+
+  // Picks spawnable data in the biome weighted by rarity, from the data the filter accepts
+  pub fn choose<F>(&self, biome: &Biome, filter: F) -> Option<&T> where
+    F: Fn(&T) -> bool,
+  {
+    let pool: Vec<&T> = self.current.get(&(biome.clone(), self.current_level))?
+      .iter().map(|id| self.get_data(*id)).filter(|data| filter(data)).collect();
+    return pool.choose_weighted(&mut rand::thread_rng(), |data| data.rarity()).ok().map(|data| *data);
+  }
+
+  pub fn current_level(&self) -> u8 {
+    return self.current_level;
+  }
+
+  pub fn data_loaded(&self) -> bool {
+    return self.data_loaded;
+  }
+
+  pub fn contains(&self, id: u64) -> bool {
+    return self.data.contains_key(&id);
+  }
+
+  pub fn get_data(&self, id: u64) -> &T {
+    return self.data.get(&id).unwrap_or(&self.error_data);
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_data_loader {
+  use crate::filesystem;
+  use crate::context::data_loader::*;
+  use crate::context::unit_loader::{UnitData, UnitLoader};
+  use crate::test_main::TempRoot;
+
+  // Unit loader with the given units file loaded from a temp data root
+  fn loaded(name: &str, units: &str) -> UnitLoader {
+    let temp = TempRoot::new(&format!("loader_{}", name));
+    filesystem::create_folder(format!("{}/units", temp.root())).unwrap();
+    filesystem::create_file(temp.paths.units_file(), units.to_owned()).unwrap();
+    let mut loader = UnitLoader::new();
+    loader.load_data(&temp.paths).unwrap();
+    return loader;
+  }
+
+  #[test]
+  fn test_choose_weighted_by_rarity() {
+    let mut loader = loaded("weighted", "ID ,Name ,Level Range ,Biomes ,Rarity\n1 ,Rare ,0-9 ,POND ,1\n2 ,Common ,0-9 ,POND ,9\n");
+    loader.update_current(&RotfPlayer::new());
+    let picks = 4000;
+    let rare = (0..picks).filter(|_| loader.choose(&Biome::POND, |_: &UnitData| true).unwrap().id == 1).count();
+    // expect a tenth, within a wide margin so the test doesn't flake
+    assert!(rare > picks / 20 && rare < picks * 3 / 20, "rare unit chosen {} times in {}", rare, picks);
+  }
+
+  fn player(level: u8) -> RotfPlayer {
+    let mut player = RotfPlayer::new();
+    player.level = level;
+    return player;
+  }
+
+  #[test]
+  fn test_update_current() {
+    let mut loader = loaded("current", "Tier ,ID ,Name ,Level Range ,Biomes
+      1 ,1 ,Frog ,0-3 ,POND
+  ,2 ,Toad ,4-6 ,POND
+  ,3 ,Eft ,0-3 ,STREAM;POND
+2 ,4 ,Newt ,10-12 ,POND
+");
+    loader.update_current(&player(2));
+    let mut pond = loader.current[&(Biome::POND, 2)].clone();
+    pond.sort();
+    assert_eq!(pond, vec![1, 3]);
+    assert_eq!(loader.current[&(Biome::STREAM, 2)], vec![3]);
+    assert!(loader.current[&(Biome::MARSH, 2)].is_empty());
+    loader.update_current(&player(5));
+    assert_eq!(loader.current[&(Biome::POND, 5)], vec![2]);
+    // tier 2 data only spawns for a tier 2 player
+    loader.update_current(&player(10));
+    assert_eq!(loader.current_level(), 10);
+    assert_eq!(loader.current[&(Biome::POND, 10)], vec![4]);
+  }
+
+  #[test]
+  fn test_update_current_caches_pools() {
+    let mut loader = loaded("cached", "ID ,Name ,Level Range ,Biomes
+1 ,Frog ,0-3 ,POND
+");
+    loader.update_current(&player(2));
+    loader.data.clear();
+    // pools already built for a level are kept until a reload
+    loader.update_current(&player(2));
+    assert_eq!(loader.current[&(Biome::POND, 2)], vec![1]);
+    loader.update_current(&player(3));
+    assert!(loader.current[&(Biome::POND, 3)].is_empty());
+  }
+
+  #[test]
+  fn test_choose_empty_pool() {
+    let mut loader = loaded("empty", "ID ,Name ,Level Range ,Biomes
+1 ,Frog ,0-3 ,POND
+");
+    // no pools before update_current
+    assert!(loader.choose(&Biome::POND, |_: &UnitData| true).is_none());
+    loader.update_current(&player(2));
+    assert!(loader.choose(&Biome::FOREST_FLOOR, |_: &UnitData| true).is_none());
+    assert!(loader.choose(&Biome::POND, |_: &UnitData| false).is_none());
+    assert_eq!(loader.choose(&Biome::POND, |_: &UnitData| true).unwrap().id, 1);
+  }
+
+  #[test]
+  fn test_get_data_unknown_id() {
+    let loader = loaded("unknown", "ID ,Name ,Level Range ,Biomes
+1 ,Frog ,0-3 ,POND
+");
+    assert!(!loader.contains(99));
+    assert!(loader.get_data(99) == &UnitData::error_data());
+    assert_eq!(loader.get_data(1).name(), "Frog");
+  }
+}
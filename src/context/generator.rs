@@ -0,0 +1,185 @@
+use std::cmp::max;
+use std::fmt;
+use std::io::Error;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::csv::{self, CsvRow, CsvParseError};
+use crate::numeric::IntegerRange;
+
+
+// Columns a template row must contain; other columns are copied to every row it generates
+pub const TEMPLATE_COLUMNS: &[&str] = &["IDs", "Name", "Level Range"];
+// Stat columns scaled by size class and growth
+const STAT_COLUMNS: [&str; 5] = ["Health", "Attack", "Magic", "Defense", "Resistance"];
+
+
+// Size of a generated unit or item, which scales its stats and shifts its levels
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum SizeClass {
+  TINY,
+  SMALL,
+  MEDIUM,
+  LARGE,
+  GIANT,
+}
+
+impl fmt::Display for SizeClass {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for SizeClass {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for size in SizeClass::iter() {
+      if size.to_string() == s {
+        return Ok(size);
+      }
+    }
+    Err(())
+  }
+}
+
+impl SizeClass {
+  // Word added to the name; medium things don't get one
+  fn prefix(&self) -> &'static str {
+    match self {
+      SizeClass::TINY => "Tiny",
+      SizeClass::SMALL => "Small",
+      SizeClass::MEDIUM => "",
+      SizeClass::LARGE => "Large",
+      SizeClass::GIANT => "Giant",
+    }
+  }
+
+  fn stat_factor(&self) -> f64 {
+    match self {
+      SizeClass::TINY => 0.5,
+      SizeClass::SMALL => 0.75,
+      SizeClass::MEDIUM => 1.0,
+      SizeClass::LARGE => 1.5,
+      SizeClass::GIANT => 2.0,
+    }
+  }
+
+  fn level_offset(&self) -> i64 {
+    match self {
+      SizeClass::TINY => -2,
+      SizeClass::SMALL => -1,
+      SizeClass::MEDIUM => 0,
+      SizeClass::LARGE => 1,
+      SizeClass::GIANT => 2,
+    }
+  }
+}
+
+
+// Reads a templates file and expands every template row
+pub fn generate(file: String) -> Result<Vec<CsvRow>, Error> {
+  let mut templates = csv::parse_file(file, TEMPLATE_COLUMNS)?;
+  csv::carry_forward(&mut templates, "Tier");
+  let mut rows = Vec::new();
+  for template in templates {
+    rows.append(&mut expand(&template)?);
+  }
+  return Ok(rows);
+}
+
+// Makes a data row for each id in the template's IDs range, each named with a different adjective
+// and size class; which id gets which name depends only on the template's seed
+pub fn expand(template: &CsvRow) -> Result<Vec<CsvRow>, CsvParseError> {
+  let ids = template.parse_with("IDs", "a range of ids above 0 like 8-11", |s| {
+    IntegerRange::parse(s).and_then(|range| if range.min() > 0 { Ok(range) } else { Err(()) })
+  })?;
+  let level_range = template.parse_with("Level Range", "a level like 3 or a range like 0-5", IntegerRange::parse)?;
+  let mut adjectives = template.parse_list::<String>("Adjectives", "a ';' separated list of words")?;
+  if adjectives.is_empty() {
+    adjectives.push("".to_owned());
+  }
+  let mut sizes = template.parse_list::<SizeClass>("Sizes", "a ';' separated list of TINY, SMALL, MEDIUM, LARGE or GIANT")?;
+  if sizes.is_empty() {
+    sizes.push(SizeClass::MEDIUM);
+  }
+  let growth = template.parse_or::<f64>("Growth", 0.0, "a percent of stats gained per level")?;
+  let seed = template.parse_or::<u64>("Seed", ids.min() as u64, "an unsigned integer")?;
+  let mut stats = Vec::new();
+  for column in STAT_COLUMNS {
+    stats.push((column, template.parse_or::<u32>(column, 0, "an unsigned integer")?));
+  }
+  let mut variants: Vec<(&String, &SizeClass)> = adjectives.iter().cartesian_product(sizes.iter()).collect();
+  if (ids.max() - ids.min() + 1) as usize > variants.len() {
+    return Err(template.error("IDs", &format!("at most {} ids, one for each adjective and size", variants.len())));
+  }
+  // ChaCha8 gives the same order on every platform and rand version, unlike StdRng
+  variants.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+  let mut rows = Vec::new();
+  for (id, (adjective, size)) in (ids.min()..=ids.max()).zip(variants) {
+    let mut row = template.clone();
+    row.set("ID", id.to_string());
+    row.set("Name", [adjective.as_str(), size.prefix(), template.get("Name")].iter()
+      .filter(|word| !word.is_empty()).join(" "));
+    let start = max(0, level_range.min() + size.level_offset());
+    let end = max(start, level_range.max() + size.level_offset());
+    row.set("Level Range", IntegerRange::between(start, end).to_string());
+    // stats grow by the growth percent for each level the size class shifts
+    let factor = size.stat_factor() * (1.0 + growth / 100.0 * (start - level_range.min()) as f64);
+    for (column, stat) in &stats {
+      row.set(column, ((*stat as f64 * factor).round().max(0.0) as u32).to_string());
+    }
+    rows.push(row);
+  }
+  return Ok(rows);
+}
+
+
+#[cfg(test)]
+pub mod test_generator {
+  use crate::filesystem;
+  use crate::context::generator::*;
+  use crate::test_main::TempRoot;
+
+  #[test]
+  fn test_generate() {
+    let temp = TempRoot::new("templates");
+    let root = temp.root();
+    let file = format!("{}/templates.csv", root);
+    filesystem::create_folder(root.clone()).unwrap();
+    filesystem::create_file(file.clone(), [
+      "Tier ,IDs  ,Name   ,Level Range ,Adjectives ,Sizes       ,Growth ,Seed ,Health",
+      "   1 ,8-11 ,Beetle ,2-6         ,Red;Blue   ,SMALL;LARGE ,10     ,7    ,40",
+      "     ,12   ,Newt   ,0           ,           ,TINY        ,       ,     ,4",
+    ].join("\n")).unwrap();
+    let rows = generate(file.clone());
+    let again = generate(file.clone());
+    filesystem::create_file(file.clone(), "IDs ,Name ,Level Range\n1-2 ,Eft ,0\n".to_owned()).unwrap();
+    let too_many = generate(file.clone());
+    let rows = rows.unwrap();
+    let summary: Vec<String> = rows.iter().map(|row| format!("{} {} {} {} {}",
+      row.get("Tier"), row.get("ID"), row.get("Name"), row.get("Level Range"), row.get("Health"))).collect();
+    // the same seed always names the ids the same way
+    assert_eq!(summary, again.unwrap().iter().map(|row| format!("{} {} {} {} {}",
+      row.get("Tier"), row.get("ID"), row.get("Name"), row.get("Level Range"), row.get("Health"))).collect::<Vec<String>>());
+    assert_eq!(rows.len(), 5);
+    assert_eq!(summary[4], "1 12 Tiny Newt 0 2");
+    // the shuffle is portable, so seed 7 names the ids the same way on every platform
+    let names: Vec<&str> = rows[..4].iter().map(|row| row.get("Name")).collect();
+    assert_eq!(names, vec!["Blue Large Beetle", "Red Small Beetle", "Blue Small Beetle", "Red Large Beetle"]);
+    for row in &rows[..4] {
+      match row.get("Name").contains("Large") {
+        true => assert_eq!((row.get("Level Range"), row.get("Health")), ("3-7", "66")),
+        false => assert_eq!((row.get("Level Range"), row.get("Health")), ("1-5", "27")),
+      }
+    }
+    assert_eq!(too_many.err().unwrap().to_string(),
+      format!("{} row 2: column 'IDs' expected at most 1 ids, one for each adjective and size, found '1-2'", file));
+  }
+}
@@ -1,129 +1,43 @@
 use std::cmp::min;
-use std::collections::HashMap;
-use std::io::{BufRead, Error};
 
-use rand::seq::SliceRandom;
+use strum::IntoEnumIterator;
 
-use crate::game::player::RotfPlayer;
+use crate::game::biome::Biome;
 use crate::numeric::{IntegerRange, random_int};
-use crate::filesystem;
+use crate::csv::{CsvRow, CsvParseError};
+use crate::paths::RotfPaths;
 
 use super::constants;
+use super::data_loader::{DataLoader, DataRow, DataStats, parse_tier};
 
 
 // Service struct that parses item data and delivers it to context
-pub struct ItemLoader {
-  item_data: HashMap<u64, ItemData>, // all items
-  error_item_data: ItemData,
-  current_items: Vec<u64>, // spawnable items (in level range)
-  current_level: u8,
-  data_loaded: bool,
-}
-
-impl ItemLoader {
-  pub fn new() -> ItemLoader {
-    return ItemLoader {
-      item_data: HashMap::new(),
-      error_item_data: ItemData::new(),
-      current_items: Vec::new(),
-      current_level: 0,
-      data_loaded: false,
-    }
-  }
-
-  pub fn load_data(&mut self) -> Result<(), Error> {
-    if self.data_loaded {
-      return Ok(())
-    }
-    for oline in filesystem::open_file(format!("data/items/data.csv"))?.lines() {
-      let data: Vec<String> = oline?.trim().split(",").map(|s| s.to_string()).collect();
-      if data.len() < 5 {
-        continue;
-      }
-      let mut item = ItemData::new();
-      // id
-      match data.get(1).unwrap().trim().parse::<u64>() {
-        Ok(id) => {
-          if id < 1 {
-            continue;
-          }
-          item.id = id;
-        },
-        Err(_) => {
-          continue;
-        }
-      }
-      // name
-      item.name = data.get(2).unwrap().trim().to_owned();
-      if item.name.is_empty() {
-        continue;
-      }
-      // level range
-      item.level_range = IntegerRange::from_str(data.get(3).unwrap().trim());
-      // spawn range
-      item.spawn_range = IntegerRange::from_str(data.get(4).unwrap().trim());
-      // add to item data
-      match self.item_data.insert(item.id, item) {
-        Some(previous_item) => {
-          return Err(Error::new(std::io::ErrorKind::Other, format!("Duplicate item id {}", previous_item.id)));
-        },
-        None => {},
-      }
-    }
-    self.data_loaded = true;
-    Ok(())
-  }
+pub type ItemLoader = DataLoader<ItemData>;
 
-  pub fn update_current_items(&mut self, player: &RotfPlayer) {
-    self.current_level = player.level;
-    self.current_items.clear();
-    for (id, item) in &self.item_data {
-      if item.level_range.contains(self.current_level.into()) {
-        self.current_items.push(*id);
-      }
+impl DataLoader<ItemData> {
+  // Returns the id, level and number of copies of the spawned item
+  pub fn spawn(&self, biome: &Biome) -> (u64, u8, usize) {
+    match self.choose(biome, |_| true) {
+      Some(item) => (item.id, item.spawn_level(self.current_level()), item.spawn_count()),
+      None => (0, 0, 0),
     }
   }
-
-  pub fn spawn(&self) -> (u64, u8) {
-    match self.current_items.choose(&mut rand::thread_rng()) {
-      Some(id) => {
-        let item = self.item_data.get(id).unwrap_or(&self.error_item_data);
-        let mut min_player_level = self.current_level;
-        if self.current_level > constants::ITEM_SPAWN_RANGE_MIN {
-          min_player_level -= constants::ITEM_SPAWN_RANGE_MIN;
-        }
-        else {
-          min_player_level = 0;
-        }
-        let min_level = min(min_player_level,
-          item.level_range.min().try_into().unwrap_or(0));
-        let mut max_player_level = self.current_level;
-        if self.current_level > constants::ITEM_SPAWN_RANGE_MAX {
-          max_player_level -= constants::ITEM_SPAWN_RANGE_MAX;
-        }
-        else {
-          max_player_level = 0;
-        }
-        let max_level = min(max_player_level,
-          item.level_range.max().try_into().unwrap_or(0));
-        return (*id, random_int(min_level, max_level));
-      },
-      None => (0, 0),
-    }
-  }
-
-  pub fn get_data(&self, id: u64) -> &ItemData {
-    return self.item_data.get(&id).unwrap_or(&self.error_item_data);
-  }
 }
 
 
 // Struct to hold a single item's data
+#[derive(Debug, PartialEq)]
 pub struct ItemData {
   pub id: u64,
   pub name: String,
+  pub tier: u8, // player tier the item spawns in
   pub level_range: IntegerRange,
   pub spawn_range: IntegerRange,
+  pub biomes: Vec<Biome>,
+  pub rarity: u32, // spawn weight; lower is rarer
+  pub stats: DataStats,
+  pub description: String,
+  pub flavor: String, // shown once the player reaches the item's level
 }
 
 impl ItemData {
@@ -131,8 +45,134 @@ impl ItemData {
     return ItemData {
       id: 0,
       name: "".to_owned(),
+      tier: 1,
       level_range: IntegerRange::new(),
       spawn_range: IntegerRange::new(),
+      biomes: Vec::new(),
+      rarity: constants::DEFAULT_SPAWN_WEIGHT,
+      stats: DataStats::new(),
+      description: "".to_owned(),
+      flavor: "".to_owned(),
+    }
+  }
+
+  // Number of copies that appear when the item spawns
+  pub fn spawn_count(&self) -> usize {
+    if self.spawn_range.min() < 0 || self.spawn_range.max() < 0 {
+      return 1;
+    }
+    return random_int(self.spawn_range.min(), self.spawn_range.max()).try_into().unwrap_or(1);
+  }
+}
+
+impl DataRow for ItemData {
+  const KIND: &'static str = "item";
+  const REQUIRED_COLUMNS: &'static [&'static str] = &["ID", "Name", "Level Range", "Spawn Range"];
+
+  fn file(paths: &RotfPaths) -> String {
+    return paths.items_file();
+  }
+
+  fn templates_file(paths: &RotfPaths) -> String {
+    return paths.items_templates_file();
+  }
+
+  fn error_data() -> ItemData {
+    return ItemData::new();
+  }
+
+  fn from_row(row: &CsvRow) -> Result<Option<ItemData>, CsvParseError> {
+    // rows without an id are spacers, id 0 is the error item and unnamed ids are reserved
+    if row.get("ID").is_empty() {
+      return Ok(None);
+    }
+    let mut item = ItemData::new();
+    item.id = row.parse::<u64>("ID", "an unsigned integer")?;
+    item.name = row.get("Name").to_owned();
+    if item.id < 1 || item.name.is_empty() {
+      return Ok(None);
+    }
+    item.tier = row.parse_with("Tier", "a tier from 1 to 255", parse_tier)?;
+    item.level_range = row.parse_with("Level Range", "a level like 3 or a range like 0-5", IntegerRange::parse)?;
+    item.spawn_range = row.parse_with("Spawn Range", "a count like 3 or a range like 1-3", IntegerRange::parse)?;
+    item.biomes = row.parse_list::<Biome>("Biomes", "a ';' separated list of biomes")?;
+    if item.biomes.is_empty() {
+      item.biomes = Biome::iter().collect();
+    }
+    item.rarity = row.parse_or::<u32>("Rarity", constants::DEFAULT_SPAWN_WEIGHT, "an unsigned integer")?;
+    item.stats = DataStats::from_row(row)?;
+    item.description = row.get("Description").to_owned();
+    item.flavor = row.get("Flavor").to_owned();
+    return Ok(Some(item));
+  }
+
+  fn id(&self) -> u64 {
+    return self.id;
+  }
+
+  fn name(&self) -> &str {
+    return &self.name;
+  }
+
+  fn tier(&self) -> u8 {
+    return self.tier;
+  }
+
+  fn level_range(&self) -> &IntegerRange {
+    return &self.level_range;
+  }
+
+  fn biomes(&self) -> &Vec<Biome> {
+    return &self.biomes;
+  }
+
+  fn rarity(&self) -> u32 {
+    return self.rarity;
+  }
+
+  fn description(&self) -> &str {
+    return &self.description;
+  }
+
+  fn flavor(&self) -> &str {
+    return &self.flavor;
+  }
+
+  fn stats(&self) -> &DataStats {
+    return &self.stats;
+  }
+
+  // Items spawn between ITEM_SPAWN_RANGE_MIN and ITEM_SPAWN_RANGE_MAX levels below the player,
+  // capped by their own level range
+  fn spawn_level(&self, player_level: u8) -> u8 {
+    let min_level = min(player_level.saturating_sub(constants::ITEM_SPAWN_RANGE_MIN),
+      self.level_range.min().try_into().unwrap_or(0));
+    let max_level = min(player_level.saturating_sub(constants::ITEM_SPAWN_RANGE_MAX),
+      self.level_range.max().try_into().unwrap_or(0));
+    return random_int(min_level, max_level);
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_item_loader {
+  use crate::context::item_loader::*;
+
+  fn item_with_spawn_range(spawn_range: &str) -> ItemData {
+    let mut item = ItemData::new();
+    item.spawn_range = IntegerRange::parse(spawn_range).unwrap_or(IntegerRange::new());
+    return item;
+  }
+
+  #[test]
+  fn test_spawn_count() {
+    let ranged = item_with_spawn_range("2-4");
+    for _ in 0..50 {
+      assert!((2..=4).contains(&ranged.spawn_count()));
     }
+    assert_eq!(item_with_spawn_range("3").spawn_count(), 3);
+    // missing or negative ranges spawn a single copy
+    assert_eq!(item_with_spawn_range("").spawn_count(), 1);
+    assert_eq!(item_with_spawn_range("-3").spawn_count(), 1);
   }
 }
\ No newline at end of file
@@ -1,119 +1,46 @@
 use std::cmp::min;
-use std::collections::HashMap;
-use std::io::{BufRead, Error};
 
-use rand::seq::SliceRandom;
+use strum::IntoEnumIterator;
 
-use crate::game::player::RotfPlayer;
+use crate::game::clock::{Activity, DayPhase};
+use crate::game::biome::Biome;
+use crate::game::weather::Weather;
 use crate::numeric::{IntegerRange, random_int};
-use crate::filesystem;
+use crate::csv::{CsvRow, CsvParseError};
+use crate::paths::RotfPaths;
 
 use super::constants;
+use super::data_loader::{DataLoader, DataRow, DataStats, parse_tier};
 
 
 // Service struct that parses non-player unit data and delivers it to context
-pub struct UnitLoader {
-  unit_data: HashMap<u64, UnitData>, // all units
-  error_unit_data: UnitData,
-  current_units: Vec<u64>, // spawnable units (in level range)
-  current_level: u8,
-  data_loaded: bool,
-}
-
-impl UnitLoader {
-  pub fn new() -> UnitLoader {
-    return UnitLoader {
-      unit_data: HashMap::new(),
-      error_unit_data: UnitData::new(),
-      current_units: Vec::new(),
-      current_level: 0,
-      data_loaded: false,
-    }
-  }
-
-  pub fn load_data(&mut self) -> Result<(), Error> {
-    if self.data_loaded {
-      return Ok(())
-    }
-    for oline in filesystem::open_file(format!("data/units/data.csv"))?.lines() {
-      let data: Vec<String> = oline?.trim().split(",").map(|s| s.to_string()).collect();
-      if data.len() < 4 {
-        continue;
-      }
-      let mut unit = UnitData::new();
-      // id
-      match data.get(1).unwrap().trim().parse::<u64>() {
-        Ok(id) => {
-          if id < 1 {
-            continue;
-          }
-          unit.id = id;
-        },
-        Err(_) => {
-          continue;
-        }
-      }
-      // name
-      unit.name = data.get(2).unwrap().trim().to_owned();
-      if unit.name.is_empty() {
-        continue;
-      }
-      // level range
-      unit.level_range = IntegerRange::from_str(data.get(3).unwrap().trim());
-      // add to unit data
-      match self.unit_data.insert(unit.id, unit) {
-        Some(previous_unit) => {
-          return Err(Error::new(std::io::ErrorKind::Other, format!("Duplicate unit id {}", previous_unit.id)));
-        },
-        None => {},
-      }
-    }
-    self.data_loaded = true;
-    Ok(())
-  }
-
-  pub fn update_current_units(&mut self, player: &RotfPlayer) {
-    self.current_level = player.level;
-    self.current_units.clear();
-    for (id, unit) in &self.unit_data {
-      if unit.level_range.contains(self.current_level.into()) {
-        self.current_units.push(*id);
-      }
-    }
-  }
+pub type UnitLoader = DataLoader<UnitData>;
 
-  pub fn spawn(&self) -> (u64, u8) {
-    match self.current_units.choose(&mut rand::thread_rng()) {
-      Some(id) => {
-        let unit = self.unit_data.get(id).unwrap_or(&self.error_unit_data);
-        let mut min_player_level = self.current_level;
-        if self.current_level > constants::UNIT_SPAWN_RANGE {
-          min_player_level -= constants::UNIT_SPAWN_RANGE;
-        }
-        else {
-          min_player_level = 0;
-        }
-        let min_level = min(min_player_level,
-          unit.level_range.min().try_into().unwrap_or(0));
-        let max_level = min(self.current_level + constants::UNIT_SPAWN_RANGE,
-          unit.level_range.max().try_into().unwrap_or(0));
-        return (*id, random_int(min_level, max_level));
-      },
+impl DataLoader<UnitData> {
+  // Returns the id and level of a unit that can spawn now, or 0s if none can
+  pub fn spawn(&self, biome: &Biome, phase: &DayPhase, weather: &Weather) -> (u64, u8) {
+    match self.choose(biome, |unit| unit.activity.is_active(phase) && unit.spawns_in(weather)) {
+      Some(unit) => (unit.id, unit.spawn_level(self.current_level())),
       None => (0, 0),
     }
   }
-
-  pub fn get_data(&self, id: u64) -> &UnitData {
-    return self.unit_data.get(&id).unwrap_or(&self.error_unit_data);
-  }
 }
 
 
 // Struct to hold a single unit's data
+#[derive(Debug, PartialEq)]
 pub struct UnitData {
   pub id: u64,
   pub name: String,
+  pub tier: u8, // player tier the unit spawns in
   pub level_range: IntegerRange,
+  pub activity: Activity,
+  pub weathers: Vec<Weather>, // empty if unit spawns in all weather
+  pub biomes: Vec<Biome>,
+  pub rarity: u32, // spawn weight; lower is rarer
+  pub stats: DataStats,
+  pub description: String,
+  pub flavor: String, // shown once the player reaches the unit's level
 }
 
 impl UnitData {
@@ -121,7 +48,107 @@ impl UnitData {
     return UnitData {
       id: 0,
       name: "".to_owned(),
+      tier: 1,
       level_range: IntegerRange::new(),
+      activity: Activity::ALWAYS,
+      weathers: Vec::new(),
+      biomes: Vec::new(),
+      rarity: constants::DEFAULT_SPAWN_WEIGHT,
+      stats: DataStats::new(),
+      description: "".to_owned(),
+      flavor: "".to_owned(),
+    }
+  }
+
+  pub fn spawns_in(&self, weather: &Weather) -> bool {
+    return self.weathers.is_empty() || self.weathers.contains(weather);
+  }
+}
+
+impl DataRow for UnitData {
+  const KIND: &'static str = "unit";
+  const REQUIRED_COLUMNS: &'static [&'static str] = &["ID", "Name", "Level Range"];
+
+  fn file(paths: &RotfPaths) -> String {
+    return paths.units_file();
+  }
+
+  fn templates_file(paths: &RotfPaths) -> String {
+    return paths.units_templates_file();
+  }
+
+  fn error_data() -> UnitData {
+    return UnitData::new();
+  }
+
+  fn from_row(row: &CsvRow) -> Result<Option<UnitData>, CsvParseError> {
+    // rows without an id are spacers, id 0 is the error unit and unnamed ids are reserved
+    if row.get("ID").is_empty() {
+      return Ok(None);
+    }
+    let mut unit = UnitData::new();
+    unit.id = row.parse::<u64>("ID", "an unsigned integer")?;
+    unit.name = row.get("Name").to_owned();
+    if unit.id < 1 || unit.name.is_empty() {
+      return Ok(None);
     }
+    unit.tier = row.parse_with("Tier", "a tier from 1 to 255", parse_tier)?;
+    unit.level_range = row.parse_with("Level Range", "a level like 3 or a range like 0-5", IntegerRange::parse)?;
+    unit.activity = row.parse::<Activity>("Active", "DAY, NIGHT or nothing")?;
+    unit.weathers = row.parse_list::<Weather>("Weather", "a ';' separated list of weathers")?;
+    unit.biomes = row.parse_list::<Biome>("Biomes", "a ';' separated list of biomes")?;
+    if unit.biomes.is_empty() {
+      unit.biomes = Biome::iter().collect();
+    }
+    unit.rarity = row.parse_or::<u32>("Rarity", constants::DEFAULT_SPAWN_WEIGHT, "an unsigned integer")?;
+    unit.stats = DataStats::from_row(row)?;
+    unit.description = row.get("Description").to_owned();
+    unit.flavor = row.get("Flavor").to_owned();
+    return Ok(Some(unit));
+  }
+
+  fn id(&self) -> u64 {
+    return self.id;
+  }
+
+  fn name(&self) -> &str {
+    return &self.name;
+  }
+
+  fn tier(&self) -> u8 {
+    return self.tier;
+  }
+
+  fn level_range(&self) -> &IntegerRange {
+    return &self.level_range;
+  }
+
+  fn biomes(&self) -> &Vec<Biome> {
+    return &self.biomes;
+  }
+
+  fn rarity(&self) -> u32 {
+    return self.rarity;
+  }
+
+  fn description(&self) -> &str {
+    return &self.description;
+  }
+
+  fn flavor(&self) -> &str {
+    return &self.flavor;
+  }
+
+  fn stats(&self) -> &DataStats {
+    return &self.stats;
+  }
+
+  // Units spawn within UNIT_SPAWN_RANGE levels of the player, capped by their own level range
+  fn spawn_level(&self, player_level: u8) -> u8 {
+    let min_level = min(player_level.saturating_sub(constants::UNIT_SPAWN_RANGE),
+      self.level_range.min().try_into().unwrap_or(0));
+    let max_level = min(player_level.saturating_add(constants::UNIT_SPAWN_RANGE),
+      self.level_range.max().try_into().unwrap_or(0));
+    return random_int(min_level, max_level);
   }
 }
\ No newline at end of file
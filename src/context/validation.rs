@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Error;
+use std::path::Path;
+
+use strum::IntoEnumIterator;
+
+use crate::csv::{self, CsvRow};
+use crate::cutscene::RotfCutscene;
+use crate::dialogue::Dialogue;
+use crate::game::player::RotfPlayer;
+use crate::paths::RotfPaths;
+
+use super::content_pack::ContentPack;
+use super::data_loader::DataRow;
+use super::generator;
+use super::item_loader::ItemData;
+use super::unit_loader::UnitData;
+
+
+// How bad a problem in the data is
+#[derive(Debug, PartialEq)]
+pub enum Severity {
+  ERROR, // data that fails to load or can't be used as intended
+  WARNING, // data that loads but looks unfinished
+}
+
+// A problem found in a data file or cutscene
+pub struct ValidationIssue {
+  pub severity: Severity,
+  pub message: String, // starts with the file and row or line
+}
+
+impl fmt::Display for ValidationIssue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}: {}", self.severity, self.message)
+  }
+}
+
+impl ValidationIssue {
+  fn error(message: String) -> ValidationIssue {
+    return ValidationIssue {
+      severity: Severity::ERROR,
+      message,
+    }
+  }
+
+  fn warning(message: String) -> ValidationIssue {
+    return ValidationIssue {
+      severity: Severity::WARNING,
+      message,
+    }
+  }
+}
+
+
+// Loads every data file, dialogue and cutscene, collecting all the problems instead of stopping at the first
+pub fn validate(paths: &RotfPaths) -> Vec<ValidationIssue> {
+  let mut issues = Vec::new();
+  let mut ids: HashMap<&'static str, HashSet<u64>> = HashMap::new();
+  let mut references = Vec::new();
+  let packs = match ContentPack::installed(paths) {
+    Ok(packs) => packs,
+    Err(e) => {
+      issues.push(ValidationIssue::error(e.to_string()));
+      Vec::new()
+    },
+  };
+  validate_data::<UnitData>(paths, &packs, &mut issues, &mut ids, &mut references);
+  validate_data::<ItemData>(paths, &packs, &mut issues, &mut ids, &mut references);
+  // npc dialogue, whose conditions can refer to items
+  match Dialogue::ids(paths) {
+    Ok(dialogue_ids) => for id in dialogue_ids {
+      match Dialogue::load(paths, &id) {
+        Ok(dialogue) => for (line, item) in dialogue.item_references() {
+          references.push((format!("{} line {}", dialogue.file, line), ItemData::KIND, item));
+        },
+        Err(e) => issues.push(ValidationIssue::error(e.to_string())),
+      }
+    },
+    Err(e) => issues.push(ValidationIssue::error(e.to_string())),
+  }
+  // references between data files
+  for (location, kind, id) in references {
+    if !ids.get(kind).map_or(false, |kind_ids| kind_ids.contains(&id)) {
+      issues.push(ValidationIssue::error(format!("{}: refers to {} id {} which isn't loaded", location, kind, id)));
+    }
+  }
+  // cutscenes
+  for cutscene in RotfCutscene::iter() {
+    let file = cutscene.file(paths);
+    if !Path::new(&file).exists() {
+      issues.push(ValidationIssue::error(format!("{}: missing cutscene file for {}", file, cutscene)));
+      continue;
+    }
+    match cutscene.validate(paths) {
+      Ok(()) => {},
+      Err(e) => issues.push(ValidationIssue::error(e.to_string())),
+    }
+  }
+  return issues;
+}
+
+// Checks the base data and then each content pack's data; each has a data file, which is optional
+// in a content pack, and an optional templates file
+fn validate_data<T: DataRow>(paths: &RotfPaths, packs: &Vec<ContentPack>, issues: &mut Vec<ValidationIssue>,
+  ids: &mut HashMap<&'static str, HashSet<u64>>, references: &mut Vec<(String, &'static str, u64)>) {
+  let mut id_rows: HashMap<u64, (String, usize)> = HashMap::new();
+  if !Path::new(&T::file(paths)).exists() {
+    issues.push(ValidationIssue::error(format!("{}: missing {} data file", T::file(paths), T::KIND)));
+  }
+  let mut sources = vec![(paths.clone(), None)];
+  sources.extend(packs.iter().map(|pack| (pack.paths(paths), Some(pack))));
+  for (source, pack) in sources {
+    if Path::new(&T::file(&source)).exists() {
+      let rows = csv::parse_file(T::file(&source), T::REQUIRED_COLUMNS).map(|mut rows| {
+        csv::carry_forward(&mut rows, "Tier");
+        rows
+      });
+      validate_rows::<T>(rows, pack, issues, &mut id_rows, ids, references);
+    }
+    if Path::new(&T::templates_file(&source)).exists() {
+      let rows = generator::generate(T::templates_file(&source));
+      validate_rows::<T>(rows, pack, issues, &mut id_rows, ids, references);
+    }
+  }
+}
+
+fn validate_rows<T: DataRow>(rows: Result<Vec<CsvRow>, Error>, pack: Option<&ContentPack>, issues: &mut Vec<ValidationIssue>,
+  id_rows: &mut HashMap<u64, (String, usize)>, ids: &mut HashMap<&'static str, HashSet<u64>>,
+  references: &mut Vec<(String, &'static str, u64)>) {
+  let rows = match rows {
+    Ok(rows) => rows,
+    Err(e) => {
+      issues.push(ValidationIssue::error(e.to_string()));
+      return;
+    },
+  };
+  for row in rows {
+    let location = format!("{} row {}", row.file, row.row);
+    if row.get("ID").is_empty() {
+      continue;
+    }
+    let id = match row.parse::<u64>("ID", "an unsigned integer") {
+      Ok(id) => id,
+      Err(e) => {
+        issues.push(ValidationIssue::error(e.to_string()));
+        continue;
+      },
+    };
+    match id_rows.get(&id) {
+      Some((first_file, first_row)) if *first_file == row.file => {
+        issues.push(ValidationIssue::error(format!("{}: {} id {} is already used on row {}", location, T::KIND, id, first_row)));
+        continue;
+      },
+      Some((first_file, first_row)) => {
+        issues.push(ValidationIssue::error(format!("{}: {} id {} is already used in {} row {}",
+          location, T::KIND, id, first_file, first_row)));
+        continue;
+      },
+      None => {
+        id_rows.insert(id, (row.file.clone(), row.row));
+      },
+    }
+    let data = match T::from_row(&row) {
+      Ok(Some(data)) => data,
+      Ok(None) => {
+        if id > 0 {
+          issues.push(ValidationIssue::warning(format!("{}: {} id {} has no name", location, T::KIND, id)));
+        }
+        continue;
+      },
+      Err(e) => {
+        issues.push(ValidationIssue::error(e.to_string()));
+        continue;
+      },
+    };
+    match pack {
+      Some(pack) if !pack.ids.contains(id as i64) => {
+        issues.push(ValidationIssue::error(format!("{}: {} id {} is outside the ids {} reserved by content pack '{}'",
+          location, T::KIND, id, pack.ids, pack.name)));
+      },
+      _ => {},
+    }
+    ids.entry(T::KIND).or_default().insert(id);
+    let tier_levels = RotfPlayer::tier_levels(data.tier());
+    if !data.level_range().overlaps(&tier_levels) {
+      issues.push(ValidationIssue::error(format!("{}: level range {} is outside tier {} levels {}",
+        location, data.level_range(), data.tier(), tier_levels)));
+    }
+    for (kind, reference) in data.references() {
+      references.push((location.clone(), kind, reference));
+    }
+  }
+}
+
+// Lists the issues followed by a count of each severity
+pub fn report(issues: &Vec<ValidationIssue>) -> String {
+  let mut report = String::new();
+  for issue in issues {
+    report += &format!("{}\n", issue);
+  }
+  let errors = issues.iter().filter(|i| i.severity == Severity::ERROR).count();
+  let warnings = issues.len() - errors;
+  if issues.is_empty() {
+    report += "No problems found in the data";
+  }
+  else {
+    report += &format!("Found {} errors and {} warnings", errors, warnings);
+  }
+  return report;
+}
+
+pub fn has_errors(issues: &Vec<ValidationIssue>) -> bool {
+  return issues.iter().any(|i| i.severity == Severity::ERROR);
+}
+
+
+#[cfg(test)]
+pub mod test_validation {
+  use crate::filesystem;
+  use crate::context::validation::*;
+  use crate::test_main::TempRoot;
+
+  // Data root in a temp folder with the given unit and item files and a valid cutscene
+  fn data_paths(name: &str, units: &str, items: &str) -> TempRoot {
+    let temp = TempRoot::new(&format!("validate_{}", name));
+    let (root, paths) = (temp.root(), &temp.paths);
+    filesystem::create_folder(format!("{}/units", root)).unwrap();
+    filesystem::create_folder(format!("{}/items", root)).unwrap();
+    filesystem::create_folder(format!("{}/cutscenes", root)).unwrap();
+    filesystem::create_file(paths.units_file(), units.to_owned()).unwrap();
+    filesystem::create_file(paths.items_file(), items.to_owned()).unwrap();
+    filesystem::create_file(paths.cutscene_file("LAUNCH_GAME"), "%%% mode = INSTANT\nHello".to_owned()).unwrap();
+    return temp;
+  }
+
+  #[test]
+  fn test_validate_data() {
+    let issues = validate(&RotfPaths::new("data".to_owned(), "data/saves".to_owned()));
+    assert!(!has_errors(&issues));
+    assert!(issues.iter().any(|i| i.to_string() == "WARNING: data/units/data.csv row 12: unit id 101 has no name"));
+  }
+
+  #[test]
+  fn test_validate_problems() {
+    let temp = data_paths("problems",
+      "Tier ,ID ,Name ,Level Range\n 1 ,1 ,Frog ,0-3\n   ,1 ,Toad ,0-3\n 2 ,2 ,Newt ,0-3\n   ,3 ,Eft ,5-1\n",
+      "ID ,Name ,Level Range ,Spawn Range\n1 ,Fly ,0-3 ,1\n");
+    let paths = &temp.paths;
+    filesystem::create_file(paths.cutscene_file("LAUNCH_GAME"), "%%% mode = SLOW\nHello".to_owned()).unwrap();
+    filesystem::create_file(paths.units_templates_file(),
+      "IDs ,Name ,Level Range ,Adjectives\n3-4 ,Newt ,0-3 ,Red;Blue\n5-7 ,Eft ,0-3 ,Red;Blue\n".to_owned()).unwrap();
+    let issues: Vec<String> = validate(paths).iter().map(|i| i.to_string()).collect();
+    let units = format!("{}/units/data.csv", paths.data_root);
+    assert_eq!(issues, vec![
+      format!("ERROR: {} row 3: unit id 1 is already used on row 2", units),
+      format!("ERROR: {} row 4: level range 0-3 is outside tier 2 levels 10-19", units),
+      format!("ERROR: {} row 5: column 'Level Range' expected a level like 3 or a range like 0-5, found '5-1'", units),
+      format!("ERROR: {} row 3: column 'IDs' expected at most 2 ids, one for each adjective and size, found '5-7'",
+        paths.units_templates_file()),
+      format!("ERROR: {}/cutscenes/LAUNCH_GAME.rotf line 1: expected a cutscene mode, found 'SLOW'", paths.data_root),
+    ]);
+  }
+
+  #[test]
+  fn test_validate_content_packs() {
+    let temp = data_paths("packs",
+      "Tier ,ID ,Name ,Level Range\n 1 ,1 ,Frog ,0-3\n",
+      "ID ,Name ,Level Range ,Spawn Range\n1 ,Fly ,0-3 ,1\n");
+    let paths = &temp.paths;
+    let pack = paths.content_pack("extra");
+    filesystem::create_folder(format!("{}/units", pack.data_root)).unwrap();
+    filesystem::create_file(paths.content_pack_file("extra"), "ids: 100-199".to_owned()).unwrap();
+    filesystem::create_file(pack.units_file(), "ID ,Name ,Level Range\n100 ,Newt ,0-3\n1 ,Toad ,0-3\n5 ,Eft ,0-3\n".to_owned()).unwrap();
+    let issues: Vec<String> = validate(paths).iter().map(|i| i.to_string()).collect();
+    assert_eq!(issues, vec![
+      format!("ERROR: {} row 3: unit id 1 is already used in {} row 2", pack.units_file(), paths.units_file()),
+      format!("ERROR: {} row 4: unit id 5 is outside the ids 100-199 reserved by content pack 'extra'", pack.units_file()),
+    ]);
+  }
+
+  #[test]
+  fn test_validate_dialogue() {
+    let temp = data_paths("dialogue",
+      "Tier ,ID ,Name ,Level Range\n 1 ,1 ,Frog ,0-3\n",
+      "ID ,Name ,Level Range ,Spawn Range\n1 ,Fly ,0-3 ,1\n");
+    let paths = &temp.paths;
+    filesystem::create_folder(paths.npcs()).unwrap();
+    filesystem::create_file(paths.npc_file("TOAD"),
+      "name: Toad\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\nchoice: Is that a fly? -> END if has item 9\n%%% END NODE".to_owned()).unwrap();
+    filesystem::create_file(paths.npc_file("NEWT"), "name: Newt\nstart: HELLO".to_owned()).unwrap();
+    let issues: Vec<String> = validate(paths).iter().map(|i| i.to_string()).collect();
+    assert_eq!(issues, vec![
+      format!("ERROR: {}: 'start' names unknown node 'HELLO'", paths.npc_file("NEWT")),
+      format!("ERROR: {} line 5: refers to item id 9 which isn't loaded", paths.npc_file("TOAD")),
+    ]);
+  }
+
+  #[test]
+  fn test_validate_missing_files() {
+    let temp = data_paths("missing", "ID ,Name\n", "");
+    let paths = &temp.paths;
+    filesystem::delete_folder(format!("{}/cutscenes", paths.data_root)).unwrap();
+    let issues: Vec<String> = validate(paths).iter().map(|i| i.to_string()).collect();
+    assert_eq!(issues, vec![
+      format!("ERROR: {}/units/data.csv row 1: column 'Level Range' expected to be in the header", paths.data_root),
+      format!("ERROR: {}/items/data.csv row 1: expected a header row", paths.data_root),
+      format!("ERROR: {}/cutscenes/LAUNCH_GAME.rotf: missing cutscene file for LAUNCH_GAME", paths.data_root),
+    ]);
+  }
+}
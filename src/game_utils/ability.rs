@@ -1,6 +1,7 @@
 use std::fmt;
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -9,7 +10,7 @@ use super::traits::DamageType;
 
 
 // Enum describing all possible combat abilities
-#[derive(Debug, EnumIter, Eq, Hash, PartialEq, Clone)]
+#[derive(Debug, EnumIter, Eq, Hash, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Ability {
   NOTHING,
 }
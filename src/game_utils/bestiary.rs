@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::data_loader::{DataLoader, DataRow};
+use crate::context::item_loader::ItemLoader;
+use crate::context::unit_loader::UnitLoader;
+use crate::rotf_file::{RotfBlock, RotfParseError};
+
+use super::clock::RotfClock;
+
+
+// Ways the player can encounter a unit or item
+#[allow(non_camel_case_types)]
+pub enum Encounter {
+  SEEN,
+  FOUGHT,
+  PICKED_UP,
+}
+
+
+// Counts of the encounters with one unit or item id
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BestiaryEntry {
+  pub seen: u64,
+  pub fought: u64,
+  pub picked_up: u64,
+  pub first_seen: u64, // clock ticks when first encountered
+}
+
+impl BestiaryEntry {
+  fn new(first_seen: u64) -> BestiaryEntry {
+    return BestiaryEntry {
+      seen: 0,
+      fought: 0,
+      picked_up: 0,
+      first_seen,
+    }
+  }
+
+  fn file_content(&self, kind: &str, id: u64) -> String {
+    let mut contents = String::new();
+    contents += &format!("\n%%% BEGIN {}", kind);
+    contents += &format!("\n   id: {}", id);
+    contents += &format!("\n   seen: {}", self.seen);
+    contents += &format!("\n   fought: {}", self.fought);
+    contents += &format!("\n   picked_up: {}", self.picked_up);
+    contents += &format!("\n   first_seen: {}", self.first_seen);
+    contents += &format!("\n%%% END {}\n", kind);
+    return contents;
+  }
+
+  fn from_block(block: &mut RotfBlock) -> Result<(u64, BestiaryEntry), RotfParseError> {
+    let id = block.take("id")?.parse::<u64>("an unsigned integer")?;
+    let mut entry = BestiaryEntry::new(0);
+    for line in &block.lines {
+      match line.key.as_str() {
+        "seen"       => entry.seen       = line.parse::<u64>("an unsigned integer")?,
+        "fought"     => entry.fought     = line.parse::<u64>("an unsigned integer")?,
+        "picked_up"  => entry.picked_up  = line.parse::<u64>("an unsigned integer")?,
+        "first_seen" => entry.first_seen = line.parse::<u64>("an unsigned integer")?,
+        _ => return Err(line.unknown_key()),
+      }
+    }
+    return Ok((id, entry));
+  }
+}
+
+
+// Every unit and item id the player has encountered, kept with the player's save
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bestiary {
+  pub units: HashMap<u64, BestiaryEntry>,
+  pub items: HashMap<u64, BestiaryEntry>,
+}
+
+impl Bestiary {
+  pub fn new() -> Bestiary {
+    return Bestiary {
+      units: HashMap::new(),
+      items: HashMap::new(),
+    }
+  }
+
+  pub fn record_unit(&mut self, id: u64, encounter: Encounter, clock: &RotfClock) {
+    Bestiary::record(&mut self.units, id, encounter, clock);
+  }
+
+  pub fn record_item(&mut self, id: u64, encounter: Encounter, clock: &RotfClock) {
+    Bestiary::record(&mut self.items, id, encounter, clock);
+  }
+
+  fn record(entries: &mut HashMap<u64, BestiaryEntry>, id: u64, encounter: Encounter, clock: &RotfClock) {
+    let entry = entries.entry(id).or_insert(BestiaryEntry::new(clock.ticks()));
+    match encounter {
+      Encounter::SEEN => entry.seen += 1,
+      Encounter::FOUGHT => entry.fought += 1,
+      Encounter::PICKED_UP => entry.picked_up += 1,
+    }
+  }
+
+  // Lists the encounters in the given tier, or every tier; combat has no outcome yet, so units
+  // reveal their stats once fought and items once picked up
+  pub fn view(&self, tier: Option<u8>, unit_loader: &UnitLoader, item_loader: &ItemLoader) -> String {
+    let mut contents = "  -- Bestiary --".to_owned();
+    contents += &Bestiary::view_entries("Units", &self.units, tier, unit_loader,
+      |entry| (format!("seen {}, fought {}", entry.seen, entry.fought), entry.fought > 0));
+    contents += &Bestiary::view_entries("Items", &self.items, tier, item_loader,
+      |entry| (format!("seen {}, picked up {}", entry.seen, entry.picked_up), entry.picked_up > 0));
+    return contents;
+  }
+
+  fn view_entries<T, F>(title: &str, entries: &HashMap<u64, BestiaryEntry>, tier: Option<u8>,
+    loader: &DataLoader<T>, counts: F) -> String where
+    T: DataRow,
+    F: Fn(&BestiaryEntry) -> (String, bool),
+  {
+    let mut ids: Vec<&u64> = entries.keys()
+      .filter(|id| tier.map_or(true, |t| loader.contains(**id) && loader.get_data(**id).tier() == t)).collect();
+    ids.sort();
+    let mut contents = format!("\n{}", title);
+    if ids.is_empty() {
+      contents += "\n  None encountered yet";
+    }
+    for id in ids {
+      let entry = &entries[id];
+      let mut clock = RotfClock::new();
+      clock.set_ticks(entry.first_seen);
+      let (counts, revealed) = counts(entry);
+      if !loader.contains(*id) {
+        contents += &format!("\n  Unknown (id {}): {}, first seen {}", id, counts, clock);
+        continue;
+      }
+      let data = loader.get_data(*id);
+      contents += &format!("\n  {} (tier {}): {}, first seen {}", data.name(), data.tier(), counts, clock);
+      let stats = data.stats().to_string();
+      if revealed && !stats.is_empty() {
+        contents += &format!("\n    {}", stats);
+      }
+    }
+    return contents;
+  }
+
+  pub fn file_content(&self) -> String {
+    let mut contents = String::new();
+    for (id, entry) in &self.units {
+      contents += &entry.file_content("BESTIARY_UNIT", *id);
+    }
+    for (id, entry) in &self.items {
+      contents += &entry.file_content("BESTIARY_ITEM", *id);
+    }
+    return contents;
+  }
+
+  // Reads a BESTIARY_UNIT or BESTIARY_ITEM block
+  pub fn read_block(&mut self, block: &mut RotfBlock) -> Result<(), RotfParseError> {
+    let entries = match block.kind.as_str() {
+      "BESTIARY_UNIT" => &mut self.units,
+      "BESTIARY_ITEM" => &mut self.items,
+      _ => return Err(block.unknown_kind()),
+    };
+    let (id, entry) = BestiaryEntry::from_block(block)?;
+    entries.insert(id, entry);
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_bestiary {
+  use crate::test_main::test_paths;
+  use crate::game::bestiary::*;
+
+  #[test]
+  fn test_view() {
+    let mut unit_loader = UnitLoader::new();
+    let mut item_loader = ItemLoader::new();
+    unit_loader.load_data(&test_paths()).unwrap();
+    item_loader.load_data(&test_paths()).unwrap();
+    let mut clock = RotfClock::new();
+    let mut bestiary = Bestiary::new();
+    bestiary.record_unit(4, Encounter::SEEN, &clock);
+    clock.set_ticks(30);
+    bestiary.record_unit(4, Encounter::SEEN, &clock);
+    bestiary.record_unit(7, Encounter::SEEN, &clock);
+    bestiary.record_unit(7, Encounter::FOUGHT, &clock);
+    bestiary.record_unit(999, Encounter::SEEN, &clock);
+    assert_eq!(bestiary.view(None, &unit_loader, &item_loader), [
+      "  -- Bestiary --",
+      "Units",
+      "  Drowning Grub (tier 1): seen 2, fought 0, first seen Day 1 (DAWN)",
+      "  Minnow Alevin (tier 1): seen 1, fought 1, first seen Day 2 (DAWN)",
+      "    Health: 15, Attack: 4, Defense: 3, Resistance: 1",
+      "  Unknown (id 999): seen 1, fought 0, first seen Day 2 (DAWN)",
+      "Items",
+      "  None encountered yet",
+    ].join("\n"));
+    assert_eq!(bestiary.view(Some(2), &unit_loader, &item_loader),
+      "  -- Bestiary --\nUnits\n  None encountered yet\nItems\n  None encountered yet");
+  }
+}
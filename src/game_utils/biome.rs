@@ -0,0 +1,80 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+
+// Biome an environment is in; determines which units and items can spawn
+#[allow(non_camel_case_types)]
+#[derive(Debug, EnumIter, Eq, Hash, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Biome {
+  POND,
+  STREAM,
+  MARSH,
+  FOREST_FLOOR,
+  UNDERGROUND,
+}
+
+impl fmt::Display for Biome {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for Biome {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for biome in Biome::iter() {
+      if biome.to_string() == s {
+        return Ok(biome);
+      }
+    }
+    Err(())
+  }
+}
+
+impl Biome {
+  pub fn default() -> Biome {
+    return Biome::POND;
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_biome {
+  use crate::test_main::test_paths;
+  use crate::context::data_loader::DataRow;
+  use crate::context::unit_loader::UnitLoader;
+  use crate::game::biome::*;
+  use crate::game::clock::DayPhase;
+  use crate::game::player::RotfPlayer;
+  use crate::game::weather::Weather;
+
+  #[test]
+  fn test_parse() {
+    for biome in Biome::iter() {
+      assert_eq!(Biome::from_str(&biome.to_string()), Ok(biome));
+    }
+    assert_eq!(Biome::from_str("FOREST_FLOOR"), Ok(Biome::FOREST_FLOOR));
+    assert!(Biome::from_str("pond").is_err());
+    assert!(Biome::from_str("DESERT").is_err());
+  }
+
+  #[test]
+  fn test_spawn_pools_keyed_by_biome() {
+    let mut loader = UnitLoader::new();
+    loader.load_data(&test_paths()).unwrap();
+    let mut player = RotfPlayer::new();
+    player.level = 3;
+    loader.update_current(&player);
+    // only units that list the biome spawn in it
+    for _ in 0..50 {
+      let (id, _) = loader.spawn(&Biome::STREAM, &DayPhase::DAY, &Weather::CLEAR);
+      assert!(loader.get_data(id).biomes().contains(&Biome::STREAM), "unit {} spawned in STREAM", id);
+    }
+    assert_eq!(loader.spawn(&Biome::FOREST_FLOOR, &DayPhase::DAY, &Weather::CLEAR), (0, 0));
+  }
+}
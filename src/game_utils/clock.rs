@@ -0,0 +1,182 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::context::constants;
+
+
+// Phase of the day determined by the game clock
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum DayPhase {
+  DAWN,
+  DAY,
+  DUSK,
+  NIGHT,
+}
+
+impl fmt::Display for DayPhase {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for DayPhase {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for phase in DayPhase::iter() {
+      if phase.to_string() == s {
+        return Ok(phase);
+      }
+    }
+    Err(())
+  }
+}
+
+impl DayPhase {
+  pub fn from_hour(hour: u64) -> DayPhase {
+    match hour {
+      5..=7 => DayPhase::DAWN,
+      8..=17 => DayPhase::DAY,
+      18..=20 => DayPhase::DUSK,
+      _ => DayPhase::NIGHT,
+    }
+  }
+}
+
+
+// When a unit is active during the day
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum Activity {
+  ALWAYS,
+  DIURNAL,
+  NOCTURNAL,
+}
+
+impl fmt::Display for Activity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for Activity {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "" => return Ok(Activity::ALWAYS),
+      "DAY" => return Ok(Activity::DIURNAL),
+      "NIGHT" => return Ok(Activity::NOCTURNAL),
+      _ => {},
+    }
+    for activity in Activity::iter() {
+      if activity.to_string() == s {
+        return Ok(activity);
+      }
+    }
+    Err(())
+  }
+}
+
+impl Activity {
+  pub fn is_active(&self, phase: &DayPhase) -> bool {
+    match self {
+      Activity::ALWAYS => true,
+      Activity::DIURNAL => *phase != DayPhase::NIGHT,
+      Activity::NOCTURNAL => *phase != DayPhase::DAY,
+    }
+  }
+}
+
+
+// Persistent game clock; one tick is one hour of game time
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RotfClock {
+  ticks: u64,
+}
+
+impl fmt::Display for RotfClock {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Day {} ({})", self.day(), self.phase())
+  }
+}
+
+impl RotfClock {
+  pub fn new() -> RotfClock {
+    return RotfClock {
+      ticks: constants::CLOCK_START_TICK,
+    }
+  }
+
+  pub fn pass_time(&mut self, ticks: u64) {
+    self.ticks += ticks;
+  }
+
+  pub fn ticks(&self) -> u64 {
+    return self.ticks;
+  }
+
+  pub fn set_ticks(&mut self, ticks: u64) {
+    self.ticks = ticks;
+  }
+
+  pub fn day(&self) -> u64 {
+    return 1 + self.ticks / constants::TICKS_PER_DAY;
+  }
+
+  pub fn hour(&self) -> u64 {
+    return self.ticks % constants::TICKS_PER_DAY;
+  }
+
+  pub fn phase(&self) -> DayPhase {
+    return DayPhase::from_hour(self.hour());
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_clock {
+  use rstest::*;
+  use crate::game::clock::*;
+
+  #[test]
+  fn test_ticks_wrap_into_days() {
+    let mut clock = RotfClock::new();
+    assert_eq!((clock.day(), clock.hour(), clock.phase()), (1, 6, DayPhase::DAWN));
+    clock.pass_time(17);
+    assert_eq!((clock.day(), clock.hour()), (1, 23));
+    clock.pass_time(1);
+    assert_eq!((clock.day(), clock.hour(), clock.phase()), (2, 0, DayPhase::NIGHT));
+    clock.set_ticks(3 * constants::TICKS_PER_DAY + 12);
+    assert_eq!(clock.to_string(), "Day 4 (DAY)");
+  }
+
+  #[rstest]
+  #[case(4, DayPhase::NIGHT)]
+  #[case(5, DayPhase::DAWN)]
+  #[case(7, DayPhase::DAWN)]
+  #[case(8, DayPhase::DAY)]
+  #[case(17, DayPhase::DAY)]
+  #[case(18, DayPhase::DUSK)]
+  #[case(20, DayPhase::DUSK)]
+  #[case(21, DayPhase::NIGHT)]
+  #[case(0, DayPhase::NIGHT)]
+  fn test_phase_from_hour(#[case] hour: u64, #[case] phase: DayPhase) {
+    assert_eq!(DayPhase::from_hour(hour), phase);
+  }
+
+  #[test]
+  fn test_is_active() {
+    for phase in DayPhase::iter() {
+      assert!(Activity::ALWAYS.is_active(&phase));
+      assert_eq!(Activity::DIURNAL.is_active(&phase), phase != DayPhase::NIGHT);
+      assert_eq!(Activity::NOCTURNAL.is_active(&phase), phase != DayPhase::DAY);
+    }
+    assert_eq!(Activity::from_str("NIGHT"), Ok(Activity::NOCTURNAL));
+    assert_eq!(Activity::from_str(""), Ok(Activity::ALWAYS));
+    assert!(Activity::from_str("NOON").is_err());
+  }
+}
@@ -1,9 +1,10 @@
-use std::{io::{BufRead, Write, Error}, fmt, str::FromStr};
+use std::{io::{BufRead, Write, Error, ErrorKind}, fmt, str::FromStr};
 
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use crate::{context::{RotfContext, ContextState}, game::GameState, filesystem};
+use crate::{context::{RotfContext, ContextState, content_pack::ContentPack}, game::GameState, filesystem, paths::RotfPaths};
 
 
 // CutsceneMode determines how cutscene is played
@@ -44,7 +45,7 @@ impl CutsceneMode {
 
 // RotfCutscene lists all possible cutscenes
 #[allow(non_camel_case_types)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum RotfCutscene {
   LAUNCH_GAME,
 }
@@ -55,6 +56,19 @@ impl fmt::Display for RotfCutscene {
   }
 }
 
+impl FromStr for RotfCutscene {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for cutscene in RotfCutscene::iter() {
+      if cutscene.to_string() == s {
+        return Ok(cutscene);
+      }
+    }
+    Err(())
+  }
+}
+
 impl RotfCutscene {
   pub fn resolve_context<R, W, E>(context: &mut RotfContext<R, W, E>) where
     R: BufRead,
@@ -70,6 +84,7 @@ impl RotfCutscene {
       Some(game) => {
         if game.state == GameState::CUTSCENE {
           game.state = GameState::ENVIRONMENT;
+          game.dirty = true;
           play_cutscene = true;
           cutscene = game.last_cutscene.clone();
         }
@@ -86,6 +101,26 @@ impl RotfCutscene {
     }
   }
 
+  // The cutscene's file, which content packs can replace
+  pub fn file(&self, paths: &RotfPaths) -> String {
+    return ContentPack::cutscene_file(paths, &self.to_string());
+  }
+
+  // Checks the cutscene file can be read and only uses known modes
+  pub fn validate(&self, paths: &RotfPaths) -> Result<(), Error> {
+    let file = self.file(paths);
+    for (i, oline) in filesystem::open_file(file.clone())?.lines().enumerate() {
+      let line = oline?;
+      if let Some(mode) = line.trim().strip_prefix("%%% mode =") {
+        if CutsceneMode::from_str(mode.trim()).is_err() {
+          return Err(Error::new(ErrorKind::InvalidData,
+            format!("{} line {}: expected a cutscene mode, found '{}'", file, i + 1, mode.trim())));
+        }
+      }
+    }
+    Ok(())
+  }
+
   pub fn play<R, W, E>(&self, context: &mut RotfContext<R, W, E>) -> Result<(), Error> where
     R: BufRead,
     W: Write,
@@ -93,7 +128,7 @@ impl RotfCutscene {
   {
     context.lnprint_sleep("");
     let mut mode = CutsceneMode::default();
-    for oline in filesystem::open_file(format!("data/cutscenes/{}.rotf", self))?.lines() {
+    for oline in filesystem::open_file(self.file(&context.paths))?.lines() {
       let line = oline?;
       if line.trim().starts_with("%%% mode =") {
         mode = CutsceneMode::from_str(line.split_once("=")
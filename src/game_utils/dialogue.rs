@@ -0,0 +1,454 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write, Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::context::{RotfContext, content_pack::ContentPack};
+use crate::cutscene::RotfCutscene;
+use crate::filesystem;
+use crate::game::{GameState, RotfGame};
+use crate::game::biome::Biome;
+use crate::game::player::RotfPlayer;
+use crate::paths::RotfPaths;
+use crate::rotf_file::{self, RotfBlock, RotfEntry, RotfLine, RotfParseError};
+
+
+// Choosing a reply that leads here ends the conversation
+const END_NODE: &str = "END";
+
+
+// Condition on the player's state for a reply to be offered
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq)]
+pub enum Condition {
+  LEVEL_AT_LEAST(u8),
+  LEVEL_BELOW(u8),
+  TIER_AT_LEAST(u8),
+  HAS_ITEM(u64), // an item with this id is in the inventory
+}
+
+impl FromStr for Condition {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    match words.as_slice() {
+      ["level", ">=", n] => n.parse::<u8>().map(Condition::LEVEL_AT_LEAST).map_err(|_| ()),
+      ["level", "<", n] => n.parse::<u8>().map(Condition::LEVEL_BELOW).map_err(|_| ()),
+      ["tier", ">=", n] => n.parse::<u8>().map(Condition::TIER_AT_LEAST).map_err(|_| ()),
+      ["has", "item", id] => id.parse::<u64>().map(Condition::HAS_ITEM).map_err(|_| ()),
+      _ => Err(()),
+    }
+  }
+}
+
+impl Condition {
+  pub fn met(&self, player: &RotfPlayer) -> bool {
+    match self {
+      Condition::LEVEL_AT_LEAST(level) => player.level >= *level,
+      Condition::LEVEL_BELOW(level) => player.level < *level,
+      Condition::TIER_AT_LEAST(tier) => player.tier() >= *tier,
+      Condition::HAS_ITEM(id) => player.inventory.list().iter().any(|item| item.id() == *id),
+    }
+  }
+}
+
+
+// What picking a reply does besides moving the conversation on; new kinds like quests or shops
+// only need a variant here, written after 'then' in a reply
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq)]
+pub enum Action {
+  CUTSCENE(RotfCutscene), // plays the cutscene, ending the conversation
+}
+
+impl FromStr for Action {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    match words.as_slice() {
+      ["cutscene", cutscene] => RotfCutscene::from_str(cutscene).map(Action::CUTSCENE),
+      _ => Err(()),
+    }
+  }
+}
+
+impl Action {
+  // Applies the action to the game, returning whether it ends the conversation
+  pub fn run(&self, game: &mut RotfGame) -> bool {
+    match self {
+      Action::CUTSCENE(cutscene) => {
+        game.last_cutscene = cutscene.clone();
+        game.state = GameState::CUTSCENE;
+        game.dirty = true;
+        return true;
+      },
+    }
+  }
+}
+
+
+// A reply the player can pick, written 'choice: {text} -> {node}' with an optional 'if {condition}'
+// and an optional 'then {action}'
+#[derive(Debug, PartialEq)]
+pub struct Choice {
+  pub text: String,
+  pub next: String, // id of the node it leads to, or END
+  pub conditions: Vec<Condition>, // all must be met for the reply to be offered
+  pub actions: Vec<Action>, // run in order when the reply is picked
+  pub line: usize,
+}
+
+impl Choice {
+  fn parse(line: &RotfLine) -> Result<Choice, RotfParseError> {
+    let (text, rest) = match line.value.rsplit_once("->") {
+      Some((text, rest)) if !text.trim().is_empty() => (text.trim(), rest.trim()),
+      _ => return Err(line.error("a reply like 'Who are you? -> WHO' or 'Teach me -> TEACH if level >= 3'")),
+    };
+    let (rest, actions) = match rest.split_once(" then ") {
+      Some((rest, actions)) => (rest.trim(), actions.split(" and ").collect()),
+      None => (rest, Vec::new()),
+    };
+    let (next, conditions) = match rest.split_once(" if ") {
+      Some((next, conditions)) => (next.trim(), conditions.split(" and ").collect()),
+      None => (rest, Vec::new()),
+    };
+    if next.is_empty() {
+      return Err(line.error("a node id after '->'"));
+    }
+    let mut choice = Choice {
+      text: text.to_owned(),
+      next: next.to_owned(),
+      conditions: Vec::new(),
+      actions: Vec::new(),
+      line: line.line,
+    };
+    for condition in conditions {
+      match Condition::from_str(condition) {
+        Ok(c) => choice.conditions.push(c),
+        Err(_) => return Err(line.error("conditions like 'level >= 3', 'level < 3', 'tier >= 2' or 'has item 4'")),
+      }
+    }
+    for action in actions {
+      match Action::from_str(action) {
+        Ok(a) => choice.actions.push(a),
+        Err(_) => return Err(line.error("actions like 'cutscene LAUNCH_GAME'")),
+      }
+    }
+    return Ok(choice);
+  }
+
+  pub fn available(&self, player: &RotfPlayer) -> bool {
+    return self.conditions.iter().all(|condition| condition.met(player));
+  }
+}
+
+
+// What the NPC says at one point of the conversation, and the replies offered
+#[derive(Debug, PartialEq)]
+pub struct DialogueNode {
+  pub id: String,
+  pub line: usize, // line of its id
+  pub text: Vec<String>,
+  pub choices: Vec<Choice>,
+  pub cutscene: Option<RotfCutscene>, // played after the text, ending the conversation
+}
+
+impl DialogueNode {
+  fn from_block(block: &mut RotfBlock) -> Result<DialogueNode, RotfParseError> {
+    let id = block.take("id")?;
+    let mut node = DialogueNode {
+      id: id.value,
+      line: id.line,
+      text: Vec::new(),
+      choices: Vec::new(),
+      cutscene: None,
+    };
+    for line in &block.lines {
+      match line.key.as_str() {
+        "text"     => node.text.push(line.value.clone()),
+        "choice"   => node.choices.push(Choice::parse(line)?),
+        "cutscene" => node.cutscene = Some(line.parse::<RotfCutscene>("a cutscene")?),
+        _ => return Err(line.unknown_key()),
+      }
+    }
+    return Ok(node);
+  }
+}
+
+
+// Conversation tree read from data/npcs/<id>.rotf; NPCs spawn in the biomes it lists, for players in its tier
+#[derive(Debug, PartialEq)]
+pub struct Dialogue {
+  pub id: String,
+  pub file: String,
+  pub name: String,
+  pub tier: u8,
+  pub biomes: Vec<Biome>,
+  start: String,
+  nodes: HashMap<String, DialogueNode>,
+}
+
+impl Dialogue {
+  // Ids of the dialogue files in the base data and every content pack
+  pub fn ids(paths: &RotfPaths) -> Result<Vec<String>, Error> {
+    let mut ids = BTreeSet::new();
+    let mut folders = vec![paths.npcs()];
+    for pack in ContentPack::installed(paths)? {
+      folders.push(pack.paths(paths).npcs());
+    }
+    for folder in folders {
+      if !Path::new(&folder).is_dir() {
+        continue;
+      }
+      for entry in filesystem::open_folder(folder)? {
+        if entry.extension().map_or(false, |ext| ext == "rotf") {
+          ids.insert(entry.file_stem().unwrap_or_default().to_string_lossy().to_string());
+        }
+      }
+    }
+    return Ok(ids.into_iter().collect());
+  }
+
+  // Every dialogue that loads, with the errors of those that don't
+  pub fn installed(paths: &RotfPaths) -> Result<(Vec<Dialogue>, Vec<Error>), Error> {
+    let mut dialogues = Vec::new();
+    let mut errors = Vec::new();
+    for id in Dialogue::ids(paths)? {
+      match Dialogue::load(paths, &id) {
+        Ok(dialogue) => dialogues.push(dialogue),
+        Err(e) => errors.push(e),
+      }
+    }
+    return Ok((dialogues, errors));
+  }
+
+  // Reads the dialogue, which content packs can replace, and checks every reply leads to a node
+  pub fn load(paths: &RotfPaths, id: &str) -> Result<Dialogue, Error> {
+    let file = ContentPack::npc_file(paths, id);
+    let mut dialogue = Dialogue {
+      id: id.to_owned(),
+      file: file.clone(),
+      name: "".to_owned(),
+      tier: 1,
+      biomes: Vec::new(),
+      start: "".to_owned(),
+      nodes: HashMap::new(),
+    };
+    let contents = filesystem::read_file(file.clone()).map_err(|e| Error::new(e.kind(), format!("{}: {}", file, e)))?;
+    for entry in rotf_file::parse(&file, &contents)? {
+      match entry {
+        RotfEntry::Block(mut block) => match block.kind.as_str() {
+          "NODE" => {
+            let node = DialogueNode::from_block(&mut block)?;
+            match dialogue.nodes.get(&node.id) {
+              Some(other) => return Err(Error::new(ErrorKind::InvalidData, format!("{} line {}: node id '{}' is already used on line {}",
+                file, node.line, node.id, other.line))),
+              None => {},
+            }
+            dialogue.nodes.insert(node.id.clone(), node);
+          },
+          _ => return Err(block.unknown_kind().into()),
+        },
+        RotfEntry::Line(line) => match line.key.as_str() {
+          "name" => dialogue.name = line.value.clone(),
+          "tier" => dialogue.tier = line.parse::<u8>("a tier from 1 to 255")
+            .and_then(|tier| if tier > 0 { Ok(tier) } else { Err(line.error("a tier from 1 to 255")) })?,
+          "biomes" => {
+            for biome in line.value.split(";").map(|s| s.trim()).filter(|s| !s.is_empty()) {
+              match Biome::from_str(biome) {
+                Ok(b) => dialogue.biomes.push(b),
+                Err(_) => return Err(line.error("a ';' separated list of biomes").into()),
+              }
+            }
+          },
+          "start" => dialogue.start = line.value.clone(),
+          _ => return Err(line.unknown_key().into()),
+        },
+      }
+    }
+    let invalid = |message: String| Err(Error::new(ErrorKind::InvalidData, format!("{}: {}", file, message)));
+    if dialogue.name.is_empty() {
+      return invalid("missing 'name' line naming the NPC".to_owned());
+    }
+    if !dialogue.nodes.contains_key(&dialogue.start) {
+      return invalid(format!("'start' names unknown node '{}'", dialogue.start));
+    }
+    for node in dialogue.nodes.values() {
+      for choice in &node.choices {
+        if choice.next != END_NODE && !dialogue.nodes.contains_key(&choice.next) {
+          return Err(Error::new(ErrorKind::InvalidData,
+            format!("{} line {}: reply leads to unknown node '{}'", file, choice.line, choice.next)));
+        }
+      }
+    }
+    return Ok(dialogue);
+  }
+
+  // Ids of the items the dialogue's conditions refer to, with the line they're on
+  pub fn item_references(&self) -> Vec<(usize, u64)> {
+    let mut references = Vec::new();
+    for node in self.nodes.values() {
+      for choice in &node.choices {
+        for condition in &choice.conditions {
+          if let Condition::HAS_ITEM(id) = condition {
+            references.push((choice.line, *id));
+          }
+        }
+      }
+    }
+    return references;
+  }
+
+  // Runs the conversation until the player leaves, the NPC runs out of replies or a cutscene starts
+  pub fn talk<R, W, E>(&self, context: &mut RotfContext<R, W, E>) -> Result<(), Error> where
+    R: BufRead,
+    W: Write,
+    E: Write,
+  {
+    let mut node = &self.nodes[&self.start];
+    loop {
+      context.println("");
+      context.println(&format!("{}:", self.name));
+      for line in &node.text {
+        context.println(&format!("  {}", line));
+      }
+      let game = context.curr_game.as_mut().unwrap();
+      match &node.cutscene {
+        Some(cutscene) => {
+          Action::CUTSCENE(cutscene.clone()).run(game);
+          return Ok(());
+        },
+        None => {},
+      }
+      let choices: Vec<&Choice> = node.choices.iter().filter(|choice| choice.available(&game.player)).collect();
+      if choices.is_empty() {
+        return Ok(());
+      }
+      context.println("");
+      for (i, choice) in choices.iter().enumerate() {
+        context.println(&format!("  {}: {}", i + 1, choice.text));
+      }
+      context.println("  0: Leave");
+      let choice = loop {
+        context.print(" choose a reply > ");
+        let input = context.read_line()?;
+        // no more input, as at the end of a script
+        if input.is_empty() {
+          return Ok(());
+        }
+        match input.trim().parse::<usize>() {
+          Ok(0) => return Ok(()),
+          Ok(i) if i <= choices.len() => break choices[i - 1],
+          _ => context.println("Not an accepted reply"),
+        }
+      };
+      let game = context.curr_game.as_mut().unwrap();
+      let mut ended = false;
+      for action in &choice.actions {
+        ended |= action.run(game);
+      }
+      if ended || choice.next == END_NODE {
+        return Ok(());
+      }
+      node = &self.nodes[&choice.next];
+    }
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_dialogue {
+  use crate::filesystem;
+  use crate::dialogue::*;
+  use crate::game::RotfDifficulty;
+  use crate::test_main::TempRoot;
+
+  fn write_dialogue(name: &str, contents: &str) -> TempRoot {
+    let temp = TempRoot::new(&format!("dialogue_{}", name));
+    filesystem::create_folder(temp.paths.npcs()).unwrap();
+    filesystem::create_file(temp.paths.npc_file("FROG"), contents.to_owned()).unwrap();
+    return temp;
+  }
+
+  #[test]
+  fn test_load() {
+    let temp = write_dialogue("load", [
+      "name: Frog",
+      "tier: 2",
+      "biomes: POND;MARSH",
+      "start: HELLO",
+      "%%% BEGIN NODE",
+      "   id: HELLO",
+      "   text: Ribbit",
+      "   choice: Who are you? -> HELLO",
+      "   choice: Teach me -> END if level >= 3 and has item 2",
+      "   choice: Show me -> END if level >= 5 then cutscene LAUNCH_GAME",
+      "%%% END NODE",
+    ].join("\n").as_str());
+    let dialogue = Dialogue::load(&temp.paths, "FROG");
+    let ids = Dialogue::ids(&temp.paths);
+    let dialogue = dialogue.unwrap();
+    assert_eq!(ids.unwrap(), vec!["FROG"]);
+    assert_eq!(dialogue.name, "Frog");
+    assert_eq!(dialogue.tier, 2);
+    assert_eq!(dialogue.biomes, vec![Biome::POND, Biome::MARSH]);
+    let choices = &dialogue.nodes["HELLO"].choices;
+    assert_eq!(choices[1].text, "Teach me");
+    assert_eq!(choices[1].next, "END");
+    assert_eq!(choices[1].conditions, vec![Condition::LEVEL_AT_LEAST(3), Condition::HAS_ITEM(2)]);
+    assert!(choices[1].actions.is_empty());
+    assert_eq!(choices[2].next, "END");
+    assert_eq!(choices[2].conditions, vec![Condition::LEVEL_AT_LEAST(5)]);
+    assert_eq!(choices[2].actions, vec![Action::CUTSCENE(RotfCutscene::LAUNCH_GAME)]);
+    assert_eq!(dialogue.item_references(), vec![(9, 2)]);
+    let mut player = RotfPlayer::new();
+    assert!(choices[0].available(&player));
+    player.level = 3;
+    assert!(!choices[1].available(&player));
+  }
+
+  #[test]
+  fn test_load_errors() {
+    for (contents, expected) in [
+      ("name: Frog\nstart: HELLO", ": 'start' names unknown node 'HELLO'"),
+      ("start: HELLO\n%%% BEGIN NODE\nid: HELLO\n%%% END NODE", ": missing 'name' line naming the NPC"),
+      ("name: Frog\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\nchoice: Bye -> GONE\n%%% END NODE",
+        " line 5: reply leads to unknown node 'GONE'"),
+      ("name: Frog\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\nchoice: Bye -> END if happy\n%%% END NODE",
+        " line 5: key 'choice' expected conditions like 'level >= 3', 'level < 3', 'tier >= 2' or 'has item 4', found 'Bye -> END if happy'"),
+      ("name: Frog\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\nchoice: Bye -> END then dance\n%%% END NODE",
+        " line 5: key 'choice' expected actions like 'cutscene LAUNCH_GAME', found 'Bye -> END then dance'"),
+      ("name: Frog\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\n%%% END NODE\n%%% BEGIN NODE\nid: HELLO\n%%% END NODE",
+        " line 7: node id 'HELLO' is already used on line 4"),
+    ] {
+      let temp = write_dialogue("errors", contents);
+      let e = Dialogue::load(&temp.paths, "FROG").err().unwrap();
+      assert_eq!(e.to_string(), format!("{}{}", temp.paths.npc_file("FROG"), expected));
+    }
+  }
+
+  #[test]
+  fn test_installed_skips_broken() {
+    let temp = write_dialogue("installed", "name: Frog\nstart: HELLO\n%%% BEGIN NODE\nid: HELLO\n%%% END NODE");
+    let paths = &temp.paths;
+    filesystem::create_file(paths.npc_file("BROKEN"), "name: Broken\nstart: GONE".to_owned()).unwrap();
+    let (dialogues, errors) = Dialogue::installed(paths).unwrap();
+    assert_eq!(dialogues.iter().map(|d| d.id.as_str()).collect::<Vec<&str>>(), vec!["FROG"]);
+    assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<String>>(),
+      vec![format!("{}: 'start' names unknown node 'GONE'", paths.npc_file("BROKEN"))]);
+  }
+
+  #[test]
+  fn test_cutscene_action() {
+    let mut game = RotfGame::new("test".to_owned(), RotfDifficulty::default());
+    game.state = GameState::ENVIRONMENT;
+    let action = Action::from_str("cutscene LAUNCH_GAME").unwrap();
+    assert!(action.run(&mut game));
+    assert_eq!(game.state, GameState::CUTSCENE);
+    assert_eq!(game.last_cutscene, RotfCutscene::LAUNCH_GAME);
+    assert!(game.dirty);
+    assert!(Action::from_str("cutscene UNKNOWN").is_err());
+  }
+}
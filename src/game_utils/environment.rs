@@ -1,23 +1,31 @@
+use std::cmp::{max, min};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
-use std::io::{Error, BufRead};
+use std::io::Error;
 
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::context::item_loader::ItemLoader;
+use crate::dialogue::Dialogue;
 use crate::context::unit_loader::UnitLoader;
-use crate::filesystem;
+use crate::paths::RotfPaths;
+use crate::rotf_file::{self, RotfEntry, RotfLine, RotfParseError};
 
 use super::player::RotfPlayer;
 use super::unit::Unit;
 use super::item::Item;
+use super::npc::Npc;
 use super::traits::Positionable;
+use super::clock::RotfClock;
+use super::weather::{Season, Weather};
+use super::biome::Biome;
 
 
 // Relative to player in environment
-#[derive(Debug, EnumIter, PartialEq, Clone)]
+#[derive(Debug, EnumIter, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Position {
   NEAR,
   MEDIUM,
@@ -55,11 +63,20 @@ impl Position {
 
 
 // Environment player is in
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RotfEnvironment {
   pub units: HashMap<u64, Unit>,
   pub next_unit_key: u64, // will never repeat keys
   pub items: HashMap<u64, Item>,
   pub next_item_key: u64, // will never repeat keys
+  #[serde(default)]
+  pub npcs: HashMap<u64, Npc>,
+  #[serde(default = "RotfEnvironment::first_key")]
+  pub next_npc_key: u64, // will never repeat keys
+  pub biome: Biome,
+  pub clock: RotfClock,
+  pub weather: Weather,
+  weather_day: u64, // day the weather was last rolled
 
   time_passed: u8, // time that needs to pass
 }
@@ -71,6 +88,12 @@ impl RotfEnvironment {
       next_unit_key: 1,
       items: HashMap::new(),
       next_item_key: 1,
+      npcs: HashMap::new(),
+      next_npc_key: 1,
+      biome: Biome::default(),
+      clock: RotfClock::new(),
+      weather: Weather::default(),
+      weather_day: 1,
       time_passed: 0,
     }
   }
@@ -85,42 +108,123 @@ impl RotfEnvironment {
     self.next_item_key += 1;
   }
 
+  pub fn add_npc(&mut self, npc: Npc) {
+    self.npcs.insert(self.next_npc_key, npc);
+    self.next_npc_key += 1;
+  }
+
+  fn first_key() -> u64 {
+    return 1;
+  }
+
+  // NPCs stay where they spawn; one of each whose dialogue lists the biome and player's tier, returning
+  // whether any spawned
+  pub fn spawn_npcs(&mut self, player: &RotfPlayer, dialogues: &Vec<Dialogue>) -> bool {
+    let mut spawned = false;
+    for dialogue in dialogues {
+      if dialogue.tier != player.tier() || !dialogue.biomes.contains(&self.biome) || self.npcs.values().any(|npc| npc.dialogue() == dialogue.id) {
+        continue;
+      }
+      let mut npc = Npc::new(dialogue.id.clone(), dialogue.name.clone());
+      npc.randomize_position();
+      self.add_npc(npc);
+      spawned = true;
+    }
+    return spawned;
+  }
+
   pub fn pass_time(&mut self) {
     self.time_passed += 1;
+    self.clock.pass_time(1);
+  }
+
+  pub fn season(&self) -> Season {
+    return Season::from_day(self.clock.day());
+  }
+
+  // Display string for the time, season and weather
+  pub fn conditions(&self) -> String {
+    return format!("{} - {}, {}, {}", self.biome, self.clock, self.season(), self.weather);
+  }
+
+  // Roll new weather once per day
+  fn update_weather(&mut self) {
+    if self.clock.day() <= self.weather_day {
+      return;
+    }
+    self.weather = Weather::random(&self.season());
+    self.weather_day = self.clock.day();
   }
 
   pub fn initial_spawns(&mut self, player: &RotfPlayer, unit_loader: &UnitLoader, item_loader: &ItemLoader) {
     // spawn units
     let num_units = self.num_units(player.tier());
+    let phase = self.clock.phase();
     for _ in 0..num_units {
-      let (id, level) = unit_loader.spawn();
+      let (id, level) = unit_loader.spawn(&self.biome, &phase, &self.weather);
       if id < 1 {
         continue;
       }
       let mut new_unit = Unit::new(id, level);
+      new_unit.roll_elite();
       new_unit.randomize_position();
       self.add_unit(new_unit);
     }
     // spawn items
     let num_items = self.num_items(player.tier());
+    self.spawn_items(num_items, item_loader, true);
+  }
+
+  // Spawns up to num_items items, where each spawn can contain several copies of an item
+  fn spawn_items(&mut self, num_items: usize, item_loader: &ItemLoader, random_position: bool) {
+    let mut spawned = 0;
     for _ in 0..num_items {
-      let (id, level) = item_loader.spawn();
+      if spawned >= num_items {
+        break;
+      }
+      let (id, level, count) = item_loader.spawn(&self.biome);
       if id < 1 {
         continue;
       }
-      let mut new_item = Item::new(id, level);
-      new_item.randomize_position();
-      self.add_item(new_item);
+      // copies of an item spawn together
+      let mut position: Option<Position> = None;
+      for _ in 0..min(count, num_items - spawned) {
+        let mut new_item = Item::new(id, level);
+        match &position {
+          Some(p) => new_item.set_position(p.clone()),
+          None => {
+            if random_position {
+              new_item.randomize_position();
+            }
+            position = Some(new_item.position());
+          },
+        }
+        self.add_item(new_item);
+        spawned += 1;
+      }
     }
   }
 
+  // Leaves everything in the current biome behind and spawns what lives in the new one
+  pub fn travel(&mut self, biome: Biome, player: &RotfPlayer, unit_loader: &UnitLoader, item_loader: &ItemLoader) {
+    self.biome = biome;
+    self.units.clear();
+    self.items.clear();
+    self.npcs.clear();
+    self.pass_time();
+    self.initial_spawns(player, unit_loader, item_loader);
+  }
+
   pub fn update(&mut self, player: &RotfPlayer, unit_loader: &UnitLoader,
     item_loader: &ItemLoader) -> Option<u64> {
     // return unit that attacks player (if any)
     let mut attacking_unit: Option<u64> = None;
+    self.update_weather();
+    let phase = self.clock.phase();
     // allow units to move
     for (i, unit) in self.units.iter_mut() {
-      if unit.possible_move(self.time_passed.into()) {
+      let active = unit_loader.get_data(unit.id()).activity.is_active(&phase);
+      if unit.possible_move(self.time_passed.into(), active) {
         attacking_unit = Some(i.clone());
       }
     }
@@ -131,11 +235,13 @@ impl RotfEnvironment {
     if num_units > self.units.len() {
       let unit_spawns = num_units - self.units.len();
       for _ in 0..unit_spawns {
-        let (id, level) = unit_loader.spawn();
+        let (id, level) = unit_loader.spawn(&self.biome, &phase, &self.weather);
         if id < 1 {
           continue;
         }
-        self.add_unit(Unit::new(id, level));
+        let mut new_unit = Unit::new(id, level);
+        new_unit.roll_elite();
+        self.add_unit(new_unit);
       }
     }
     // allow items to move
@@ -148,13 +254,7 @@ impl RotfEnvironment {
     let num_items = self.num_items(player.tier());
     if num_items > self.items.len() {
       let item_spawns = num_items - self.items.len();
-      for _ in 0..item_spawns {
-        let (id, level) = item_loader.spawn();
-        if id < 1 {
-          continue;
-        }
-        self.add_item(Item::new(id, level));
-      }
+      self.spawn_items(item_spawns, item_loader, false);
     }
     // reset time
     self.time_passed = 0;
@@ -163,90 +263,128 @@ impl RotfEnvironment {
   }
 
   fn num_units(&self, tier: u8) -> usize {
-    match tier {
-      1 => 10,
-      _ => 0,
-    }
+    let base_units = match tier {
+      1 => 10.0,
+      _ => 0.0,
+    };
+    return (base_units * self.weather.unit_factor()).round() as usize;
   }
 
   fn num_items(&self, tier: u8) -> usize {
-    match tier {
-      1 => 3,
-      _ => 0,
-    }
+    let base_items = match tier {
+      1 => 3.0,
+      _ => 0.0,
+    };
+    return (base_items * self.weather.item_factor()).round() as usize;
   }
 
   pub fn file_content(&self) -> String {
     let mut contents = String::new();
     // environment
     contents += &format!("\ntime_passed: {}", self.time_passed);
+    contents += &format!("\nbiome: {}", self.biome);
+    contents += &format!("\nticks: {}", self.clock.ticks());
+    contents += &format!("\nweather: {}", self.weather);
+    contents += &format!("\nweather_day: {}", self.weather_day);
     // units
     contents += "\n";
-    for (i, unit) in &self.units {
-      contents += &format!("\nnext_unit_key: {}", i.clone());
+    for (key, unit) in &self.units {
       contents += "\n%%% BEGIN UNIT";
+      contents += &format!("\n   key: {}", key);
       contents += &unit.file_content();
       contents += "\n%%% END UNIT\n";
     }
     contents += &format!("\nnext_unit_key: {}", self.next_unit_key);
     // items
     contents += "\n";
-    for (i, item) in &self.items {
-      contents += &format!("\nnext_item_key: {}", i.clone());
+    for (key, item) in &self.items {
       contents += "\n%%% BEGIN ITEM";
+      contents += &format!("\n   key: {}", key);
       contents += &item.file_content();
       contents += "\n%%% END ITEM\n";
     }
-    contents += &format!("\nnext_unit_key: {}", self.next_item_key);
+    contents += &format!("\nnext_item_key: {}", self.next_item_key);
+    // npcs
+    contents += "\n";
+    for (key, npc) in &self.npcs {
+      contents += "\n%%% BEGIN NPC";
+      contents += &format!("\n   key: {}", key);
+      contents += &npc.file_content();
+      contents += "\n%%% END NPC\n";
+    }
+    contents += &format!("\nnext_npc_key: {}", self.next_npc_key);
     return contents;
   }
 
-  pub fn load(&mut self, save_name: String) -> Result<(), Error> {
-    let mut in_unit = false;
-    let mut in_item = false;
-    let mut curr_unit = Unit::new(0, 0);
-    let mut curr_item = Item::new(0, 0);
-    for oline in filesystem::open_file(format!("data/saves/{}/environment.rotf", save_name))?.lines() {
-      let line = oline?;
-      match line.trim() {
-        "%%% BEGIN UNIT" => {
-          in_unit = true;
-        }
-        "%%% END UNIT" => {
-          in_unit = false;
-          self.add_unit(curr_unit);
-          curr_unit = Unit::new(0, 0);
-        }
-        "%%% BEGIN ITEM" => {
-          in_item = true;
-        }
-        "%%% END ITEM" => {
-          in_item = false;
-          self.add_item(curr_item);
-          curr_item = Item::new(0, 0);
-        }
-        _ => {},
-      }
-      if !line.clone().contains(":") {
-        continue;
-      }
-      // check for unit or item
-      if in_unit {
-        curr_unit.read_line(line);
-        continue;
-      }
-      if in_item {
-        curr_item.read_line(line);
-        continue;
-      }
-      // environment data
-      let (key, mut value) = line.split_once(":").unwrap();
-      value = value.trim();
-      match key.trim() {
-        "time_passed" => self.time_passed = value.parse::<u8>().unwrap_or(0),
-        _ => {},
+  pub fn load(&mut self, paths: &RotfPaths, save_name: String) -> Result<(), Error> {
+    for entry in rotf_file::parse_file(paths.save_file(&save_name, "environment.rotf"))? {
+      match entry {
+        RotfEntry::Block(mut block) => match block.kind.as_str() {
+          "UNIT" => {
+            let key = block.take("key")?.parse::<u64>("an unsigned integer")?;
+            self.units.insert(key, Unit::from_block(&block)?);
+            self.next_unit_key = max(self.next_unit_key, key + 1);
+          },
+          "ITEM" => {
+            let key = block.take("key")?.parse::<u64>("an unsigned integer")?;
+            self.items.insert(key, Item::from_block(&block)?);
+            self.next_item_key = max(self.next_item_key, key + 1);
+          },
+          "NPC" => {
+            let key = block.take("key")?.parse::<u64>("an unsigned integer")?;
+            self.npcs.insert(key, Npc::from_block(&block)?);
+            self.next_npc_key = max(self.next_npc_key, key + 1);
+          },
+          _ => return Err(block.unknown_kind().into()),
+        },
+        RotfEntry::Line(line) => self.read_line(&line)?,
       }
     }
     Ok(())
   }
+
+  fn read_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "time_passed" => self.time_passed = line.parse::<u8>("an integer from 0 to 255")?,
+      "biome" => self.biome = line.parse::<Biome>("a biome")?,
+      "ticks" => self.clock.set_ticks(line.parse::<u64>("an unsigned integer")?),
+      "weather" => self.weather = line.parse::<Weather>("a weather")?,
+      "weather_day" => self.weather_day = line.parse::<u64>("an unsigned integer")?,
+      "next_unit_key" => self.next_unit_key = max(self.next_unit_key, line.parse::<u64>("an unsigned integer")?),
+      "next_item_key" => self.next_item_key = max(self.next_item_key, line.parse::<u64>("an unsigned integer")?),
+      "next_npc_key" => self.next_npc_key = max(self.next_npc_key, line.parse::<u64>("an unsigned integer")?),
+      _ => return Err(line.unknown_key()),
+    }
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_environment {
+  use crate::filesystem;
+  use crate::game::environment::*;
+  use crate::test_main::TempRoot;
+
+  #[test]
+  fn test_spawn_items() {
+    let temp = TempRoot::new("spawn_items");
+    let paths = &temp.paths;
+    filesystem::create_folder(format!("{}/items", paths.data_root)).unwrap();
+    filesystem::create_file(paths.items_file(),
+      "ID ,Name ,Level Range ,Spawn Range ,Biomes\n1 ,Fleck ,0-9 ,2 ,POND\n".to_owned()).unwrap();
+    let mut item_loader = ItemLoader::new();
+    item_loader.load_data(paths).unwrap();
+    item_loader.update_current(&RotfPlayer::new());
+    let mut environment = RotfEnvironment::new();
+    // copies spawn together, and the last spawn is cut short at the limit
+    environment.spawn_items(3, &item_loader, true);
+    assert_eq!(environment.items.len(), 3);
+    assert_eq!(environment.items[&1].position(), environment.items[&2].position());
+    assert_eq!(environment.next_item_key, 4);
+    // nothing spawns in a biome without items
+    environment.biome = Biome::STREAM;
+    environment.spawn_items(3, &item_loader, true);
+    assert_eq!(environment.items.len(), 3);
+  }
 }
@@ -1,21 +1,37 @@
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use serde::{Deserialize, Serialize};
 
+use crate::context::constants;
+use crate::context::content_pack::ContentPack;
 use crate::context::item_loader::ItemLoader;
 use crate::context::unit_loader::UnitLoader;
-use crate::filesystem;
+use crate::rotf_file::{RotfLine, RotfParseError};
 use crate::commands::Command;
 use crate::cutscene;
+use crate::dialogue::Dialogue;
+use crate::paths::RotfPaths;
 
 use std::fmt;
-use std::io::{Error, BufRead};
+use std::io::{Error, ErrorKind};
 use std::str::FromStr;
 
 use self::combat::RotfCombat;
+use self::save_backend::SaveFormat;
 
 pub mod player;
 pub mod environment;
 pub mod traits;
+pub mod clock;
+pub mod weather;
+pub mod biome;
+mod migration;
+pub mod save_writer;
+pub mod save_backend;
+pub mod save_slot;
+pub mod bestiary;
+pub mod npc;
+pub mod trash;
 mod unit;
 mod item;
 mod inventory;
@@ -24,7 +40,7 @@ mod combat;
 
 
 // GameState determines available commands
-#[derive(Debug, EnumIter, PartialEq)]
+#[derive(Clone, Debug, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum GameState {
   CUTSCENE,
   ENVIRONMENT,
@@ -52,7 +68,7 @@ impl FromStr for GameState {
 
 
 // RotfDifficulty determines a factor for the strenth of opponents
-#[derive(Debug, EnumIter, PartialEq)]
+#[derive(Debug, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum RotfDifficulty {
   PEACEFUL,
   EASY,
@@ -103,27 +119,44 @@ impl UnitIdentifier {
 
 
 // RotfGame is a struct with all game information, including the environment and player
+#[derive(Serialize, Deserialize)]
 pub struct RotfGame {
   pub name: String,
   pub state: GameState,
   pub difficulty: RotfDifficulty,
   pub last_cutscene: cutscene::RotfCutscene,
+  #[serde(default)]
+  pub content_packs: Vec<String>, // content packs the game needs to load
 
   pub player: player::RotfPlayer,
   pub environment: environment::RotfEnvironment,
+  #[serde(skip)]
   pub combat: Option<combat::RotfCombat>,
+  #[serde(skip, default = "SaveFormat::default")]
+  pub save_format: SaveFormat,
+  #[serde(skip)]
+  pub slot: String,
+  #[serde(skip)]
+  pub dirty: bool, // changed since it was last written to disk
+  #[serde(skip)]
+  pub turns_since_save: usize,
 }
 
 impl RotfGame {
   pub fn new(name: String, difficulty: RotfDifficulty) -> RotfGame {
     return RotfGame {
+      slot: save_slot::slot_id(&name),
       name,
       state: GameState::CUTSCENE,
       difficulty,
       last_cutscene: cutscene::RotfCutscene::LAUNCH_GAME,
+      content_packs: Vec::new(),
       player: player::RotfPlayer::new(),
       environment: environment::RotfEnvironment::new(),
       combat: None,
+      save_format: SaveFormat::default(),
+      dirty: false,
+      turns_since_save: 0,
     }
   }
 
@@ -145,10 +178,18 @@ impl RotfGame {
     }
     self.combat = Some(combat);
     self.state = GameState::COMBAT;
+    self.dirty = true;
   }
 
   pub fn initial_spawns(&mut self, unit_loader: &UnitLoader, item_loader: &ItemLoader) {
     self.environment.initial_spawns(&self.player, unit_loader, item_loader);
+    self.dirty = true;
+  }
+
+  pub fn spawn_npcs(&mut self, dialogues: &Vec<Dialogue>) {
+    if self.environment.spawn_npcs(&self.player, dialogues) {
+      self.dirty = true;
+    }
   }
 
   pub fn commands(&self) -> Vec<Command> {
@@ -162,7 +203,13 @@ impl RotfGame {
   pub fn update(&mut self, unit_loader: &UnitLoader, item_loader: &ItemLoader) {
     match self.state {
       GameState::ENVIRONMENT => {
-        match self.environment.update(&self.player, unit_loader, item_loader) {
+        // anything spawned takes a new key
+        let next_keys = (self.environment.next_unit_key, self.environment.next_item_key);
+        let attacking_unit = self.environment.update(&self.player, unit_loader, item_loader);
+        if next_keys != (self.environment.next_unit_key, self.environment.next_item_key) {
+          self.dirty = true;
+        }
+        match attacking_unit {
           Some(i) => {
             self.enter_combat(i, false);
           },
@@ -173,45 +220,265 @@ impl RotfGame {
     }
   }
 
-  pub fn load(name: String) -> Result<RotfGame, Error> {
-    let save_name = str::replace(name.as_str(), " ", "_");
-    let mut game = RotfGame::new(save_name.clone(), RotfDifficulty::default());
-    // load metadata
-    for oline in filesystem::open_file(format!("data/saves/{}/metadata.rotf", save_name))?.lines() {
-      let line = oline?;
-      if !line.clone().contains(":") {
-        continue;
-      }
-      let (key, mut value) = line.split_once(":").unwrap();
-      value = value.trim();
-      match key.trim() {
-        "name" => game.name = value.to_owned(),
-        "state" => game.state = GameState::from_str(value).unwrap_or(GameState::CUTSCENE),
-        "difficulty" => game.difficulty = RotfDifficulty::from_str(value).unwrap_or(RotfDifficulty::default()),
-        _ => {},
+  // Counts the units and items whose ids aren't in the loaded data, as after data is reloaded
+  pub fn missing_data(&self, unit_loader: &UnitLoader, item_loader: &ItemLoader) -> (usize, usize) {
+    let units = self.environment.units.values().filter(|unit| !unit_loader.contains(unit.id())).count();
+    let items = self.environment.items.values().chain(self.player.inventory.items.values())
+      .filter(|item| !item_loader.contains(item.id())).count();
+    return (units, items);
+  }
+
+  // Names of the content packs whose reserved ids the game's units, items or bestiary use
+  pub fn content_packs_used(&self, packs: &Vec<ContentPack>) -> Vec<String> {
+    let mut ids: Vec<u64> = self.environment.units.values().map(|unit| unit.id()).collect();
+    ids.extend(self.environment.items.values().chain(self.player.inventory.items.values()).map(|item| item.id()));
+    ids.extend(self.player.bestiary.units.keys().chain(self.player.bestiary.items.keys()));
+    return packs.iter().filter(|pack| ids.iter().any(|id| pack.ids.contains(*id as i64)))
+      .map(|pack| pack.name.clone()).collect();
+  }
+
+  // Records the content packs the game needs from now on
+  pub fn set_content_packs(&mut self, content_packs: Vec<String>) {
+    if self.content_packs != content_packs {
+      self.content_packs = content_packs;
+      self.dirty = true;
+    }
+  }
+
+  // Loads the saved game in the given slot; it keeps saving to that slot even if its name changes
+  pub fn load(paths: &RotfPaths, slot: &str) -> Result<RotfGame, Error> {
+    let format = SaveFormat::detect(paths, slot);
+    let mut game = format.backend().load(paths, slot)?;
+    game.save_format = format;
+    game.slot = slot.to_owned();
+    let installed = ContentPack::installed_names(paths)?;
+    for name in &game.content_packs {
+      if !installed.contains(name) {
+        return Err(Error::new(ErrorKind::NotFound, format!(
+          "{} needs content pack '{}', which isn't in {}", game.name, name, paths.mods())));
       }
     }
-    // load player
-    game.player.load(save_name.clone())?;
-    // load environment
-    game.environment.load(save_name.clone())?;
     Ok(game)
   }
 
-  pub fn save(&self) -> Result<(), Error> {
-    let save_name = str::replace(self.name.as_str(), " ", "_");
-    filesystem::create_folder(format!("data/saves/{}", save_name))?;
-    filesystem::create_file(format!("data/saves/{}/metadata.rotf", save_name), self.metadata_content())?;
-    filesystem::create_file(format!("data/saves/{}/player.rotf", save_name), self.player.file_content())?;
-    filesystem::create_file(format!("data/saves/{}/environment.rotf", save_name), self.environment.file_content())?;
+  fn read_metadata_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "version" => {
+        line.parse::<u32>("an unsigned integer")?;
+      },
+      "name" => self.name = line.value.clone(),
+      "state" => self.state = line.parse::<GameState>("a game state")?,
+      "difficulty" => self.difficulty = line.parse::<RotfDifficulty>("a difficulty")?,
+      "content_packs" => {
+        self.content_packs = line.value.split(";").map(|s| s.trim().to_owned()).filter(|s| !s.is_empty()).collect();
+      },
+      _ => return Err(line.unknown_key()),
+    }
     Ok(())
   }
+
+  pub fn save(&self, paths: &RotfPaths) -> Result<(), Error> {
+    self.save_format.backend().save(paths, self)
+  }
+
+  // Saves the game if it changed since it was last saved, returning whether it was written
+  pub fn save_if_dirty(&mut self, paths: &RotfPaths) -> Result<bool, Error> {
+    if !self.dirty {
+      return Ok(false);
+    }
+    self.save(paths)?;
+    self.dirty = false;
+    self.turns_since_save = 0;
+    return Ok(true);
+  }
   
   fn metadata_content(&self) -> String {
     let mut contents = String::new();
+    contents += &format!("\nversion: {}", constants::SAVE_VERSION);
     contents += &format!("\nname: {}", self.name.clone());
     contents += &format!("\nstate: {}", self.state);
     contents += &format!("\ndifficulty: {}", self.difficulty);
+    if !self.content_packs.is_empty() {
+      contents += &format!("\ncontent_packs: {}", self.content_packs.join(";"));
+    }
     return contents;
   }
 }
+
+
+#[cfg(test)]
+pub mod test_game {
+  use rand::Rng;
+  use rand::rngs::ThreadRng;
+  use rand::seq::IteratorRandom;
+  use strum::IntoEnumIterator;
+
+  use crate::filesystem;
+  use crate::test_main::test_paths;
+  use crate::game::*;
+  use crate::game::bestiary::Encounter;
+  use crate::game::biome::Biome;
+  use crate::game::environment::Position;
+  use crate::game::inventory::Inventory;
+  use crate::game::item::Item;
+  use crate::game::npc::Npc;
+  use crate::game::traits::Positionable;
+  use crate::game::unit::Unit;
+  use crate::game::weather::Weather;
+  use crate::numeric::IntegerRange;
+
+  fn random_unit(rng: &mut ThreadRng) -> Unit {
+    let mut unit = Unit::new(rng.gen_range(1..20), rng.gen());
+    unit.set_position(Position::iter().choose(rng).unwrap());
+    unit.view_index = rng.gen_range(-1..20);
+    unit.roll_elite();
+    if rng.gen() {
+      unit.mark_seen();
+    }
+    return unit;
+  }
+
+  fn random_item(rng: &mut ThreadRng) -> Item {
+    let mut item = Item::new(rng.gen_range(1..20), rng.gen());
+    item.set_position(Position::iter().choose(rng).unwrap());
+    item.view_index = rng.gen_range(-1..20);
+    if rng.gen() {
+      item.mark_seen();
+    }
+    return item;
+  }
+
+  fn random_game(name: &str, rng: &mut ThreadRng) -> RotfGame {
+    let mut game = RotfGame::new(name.to_owned(), RotfDifficulty::iter().choose(rng).unwrap());
+    game.state = GameState::iter().choose(rng).unwrap();
+    // player
+    game.player.level = rng.gen();
+    game.player.view_distance = Position::iter().choose(rng).unwrap();
+    game.player.inventory.capacity = rng.gen_range(0..10);
+    for _ in 0..rng.gen_range(0..5) {
+      game.player.inventory.insert(rng.gen_range(1..100), random_item(rng));
+    }
+    game.player.inventory.next_item_key += rng.gen_range(0..5);
+    for _ in 0..rng.gen_range(0..10) {
+      let encounter = [Encounter::SEEN, Encounter::FOUGHT, Encounter::PICKED_UP].into_iter().choose(rng).unwrap();
+      game.environment.clock.set_ticks(rng.gen_range(0..100000));
+      if rng.gen() {
+        game.player.bestiary.record_unit(rng.gen_range(1..20), encounter, &game.environment.clock);
+      }
+      else {
+        game.player.bestiary.record_item(rng.gen_range(1..20), encounter, &game.environment.clock);
+      }
+    }
+    // environment
+    for _ in 0..rng.gen_range(0..5) {
+      game.environment.pass_time();
+    }
+    game.environment.clock.set_ticks(rng.gen_range(0..100000));
+    game.environment.weather = Weather::iter().choose(rng).unwrap();
+    game.environment.biome = Biome::iter().choose(rng).unwrap();
+    for _ in 0..rng.gen_range(0..20) {
+      let key = rng.gen_range(1..1000);
+      game.environment.units.insert(key, random_unit(rng));
+      game.environment.next_unit_key = game.environment.next_unit_key.max(key + rng.gen_range(1..5));
+    }
+    for _ in 0..rng.gen_range(0..10) {
+      let key = rng.gen_range(1..1000);
+      game.environment.items.insert(key, random_item(rng));
+      game.environment.next_item_key = game.environment.next_item_key.max(key + rng.gen_range(1..5));
+    }
+    for _ in 0..rng.gen_range(0..3) {
+      let mut npc = Npc::new(format!("NPC_{}", rng.gen_range(1..100)), "Old Toad".to_owned());
+      npc.set_position(Position::iter().choose(rng).unwrap());
+      npc.view_index = rng.gen_range(-1..20);
+      game.environment.add_npc(npc);
+    }
+    game.environment.next_npc_key += rng.gen_range(0..5);
+    return game;
+  }
+
+  #[test]
+  fn test_save_load_round_trip() {
+    let mut rng = rand::thread_rng();
+    for format in SaveFormat::iter() {
+      for _ in 0..25 {
+        let mut game = random_game("test round trip", &mut rng);
+        game.save_format = format.clone();
+        game.save(&test_paths()).unwrap();
+        let loaded = RotfGame::load(&test_paths(), "test_round_trip").unwrap();
+        assert_eq!(loaded.save_format, format);
+        assert_eq!(loaded.name, game.name);
+        assert_eq!(loaded.state, game.state);
+        assert_eq!(loaded.difficulty, game.difficulty);
+        assert_eq!(loaded.player, game.player);
+        assert_eq!(loaded.environment, game.environment);
+      }
+      filesystem::delete_folder(test_paths().save("test_round_trip")).unwrap();
+      save_writer::delete_backups(&test_paths(), "test_round_trip").unwrap();
+    }
+  }
+
+  #[test]
+  fn test_missing_data() {
+    let mut unit_loader = UnitLoader::new();
+    let mut item_loader = ItemLoader::new();
+    unit_loader.load_data(&test_paths()).unwrap();
+    item_loader.load_data(&test_paths()).unwrap();
+    let mut game = RotfGame::new("missing".to_owned(), RotfDifficulty::default());
+    game.environment.add_unit(Unit::new(1, 0));
+    game.environment.add_unit(Unit::new(999, 0));
+    game.environment.add_item(Item::new(1, 0));
+    game.player.inventory.capacity = 2;
+    game.player.inventory.add(Item::new(999, 0));
+    assert_eq!(game.missing_data(&unit_loader, &item_loader), (1, 1));
+  }
+
+  #[test]
+  fn test_content_packs_used() {
+    let packs = vec![
+      ContentPack { name: "units".to_owned(), ids: IntegerRange::parse("1000-1999").unwrap() },
+      ContentPack { name: "items".to_owned(), ids: IntegerRange::parse("2000-2999").unwrap() },
+      ContentPack { name: "seen".to_owned(), ids: IntegerRange::parse("3000-3999").unwrap() },
+      ContentPack { name: "unused".to_owned(), ids: IntegerRange::parse("4000-4999").unwrap() },
+    ];
+    let mut game = RotfGame::new("packs".to_owned(), RotfDifficulty::default());
+    game.environment.add_unit(Unit::new(1, 0));
+    assert!(game.content_packs_used(&packs).is_empty());
+    game.environment.add_unit(Unit::new(1500, 0));
+    game.player.inventory.add(Item::new(2500, 0));
+    game.player.bestiary.record_item(3500, Encounter::SEEN, &game.environment.clock);
+    assert_eq!(game.content_packs_used(&packs), vec!["units", "items", "seen"]);
+  }
+
+  #[test]
+  fn test_load_missing_content_pack() {
+    let mut game = RotfGame::new("test needs pack".to_owned(), RotfDifficulty::default());
+    game.set_content_packs(vec!["extra".to_owned()]);
+    game.save(&test_paths()).unwrap();
+    let saved = filesystem::read_file(test_paths().save_file(&game.slot, "metadata.rotf")).unwrap();
+    let loaded = RotfGame::load(&test_paths(), &game.slot);
+    filesystem::delete_folder(test_paths().save(&game.slot)).unwrap(); // clean up test
+    save_writer::delete_backups(&test_paths(), &game.slot).unwrap();
+    assert!(saved.contains("\ncontent_packs: extra"));
+    assert_eq!(loaded.err().unwrap().to_string(), "test needs pack needs content pack 'extra', which isn't in data/mods");
+  }
+
+  #[test]
+  fn test_inspect() {
+    let mut unit_loader = UnitLoader::new();
+    let mut item_loader = ItemLoader::new();
+    unit_loader.load_data(&test_paths()).unwrap();
+    item_loader.load_data(&test_paths()).unwrap();
+    let grub = Unit::new(4, 2);
+    assert_eq!(grub.inspect(&unit_loader, 0),
+      "Drowning Grub (2)\n  A fat beetle grub kicking at the surface\n  Health: 10, Attack: 2, Defense: 2");
+    assert_eq!(grub.inspect(&unit_loader, 2),
+      "Drowning Grub (2)\n  A fat beetle grub kicking at the surface\n  Health: 10, Attack: 2, Defense: 2\n  \"It was never meant to swim\"");
+    assert_eq!(Unit::new(7, 8).inspect(&unit_loader, 0),
+      "Minnow Alevin (8)\n  A newly hatched minnow with its yolk sac still attached\n  Stats unknown until you are level 5");
+    let mut inventory = Inventory::new();
+    inventory.add(Item::new(1, 0));
+    assert_eq!(inventory.get(1).unwrap().inspect(&item_loader, 0),
+      "Fleck (0)\n  A speck of something edible drifting in the water\n  \"Tadpoles grow up on flecks like this\"");
+    assert!(inventory.get(2).is_none());
+  }
+}
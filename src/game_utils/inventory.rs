@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
 use super::item::Item;
 
 
 // Contains infinite items separable by group
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Inventory {
   pub items: HashMap<u64, Item>,
   pub next_item_key: u64, // will never repeat keys
@@ -19,6 +22,14 @@ impl Inventory {
     }
   }
 
+  // Inserts an item under a known key, as when loading a save
+  pub fn insert(&mut self, key: u64, item: Item) {
+    self.items.insert(key, item);
+    if key >= self.next_item_key {
+      self.next_item_key = key + 1;
+    }
+  }
+
   pub fn can_pickup(&self) -> bool {
     return self.items.len() < self.capacity;
   }
@@ -32,23 +43,58 @@ impl Inventory {
     return Some(item);
   }
 
+  // Removes the item at the index shown by the inventory command
   pub fn drop(&mut self, index: i64) -> Option<Item> {
-    let mut remove_index = 0;
-    for (i, item) in self.items.iter() {
-      if index != item.view_index {
-        continue;
-      }
-      remove_index = *i;
-      break;
-    }
-    return self.items.remove(&remove_index);
+    let key = self.key(index)?;
+    return self.items.remove(&key);
   }
 
+  // Item at the index shown by the inventory command
+  pub fn get(&self, index: i64) -> Option<&Item> {
+    let key = self.key(index)?;
+    return self.items.get(&key);
+  }
+
+  // Items in the order the inventory command lists them
   pub fn list(&self) -> Vec<&Item> {
-    let mut list = vec![];
-    for (_, item) in self.items.iter() {
-      list.push(item);
+    return self.keys().iter().map(|key| &self.items[key]).collect();
+  }
+
+  fn keys(&self) -> Vec<u64> {
+    let mut keys: Vec<u64> = self.items.keys().cloned().collect();
+    keys.sort();
+    return keys;
+  }
+
+  // Key of the item at the index shown by the inventory command, starting from 1
+  fn key(&self, index: i64) -> Option<u64> {
+    if index < 1 {
+      return None;
+    }
+    return self.keys().get(index as usize - 1).cloned();
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_inventory {
+  use crate::game::inventory::*;
+
+  #[test]
+  fn test_indexes() {
+    let mut inventory = Inventory::new();
+    inventory.capacity = 3;
+    for level in 0..3 {
+      inventory.add(Item::new(1, level));
     }
-    return list;
+    // get and drop use the indexes the inventory lists
+    let levels: Vec<u8> = inventory.list().iter().map(|item| item.level).collect();
+    assert_eq!(levels, vec![0, 1, 2]);
+    assert_eq!(inventory.get(2).unwrap().level, 1);
+    assert_eq!(inventory.drop(2).unwrap().level, 1);
+    assert_eq!(inventory.get(2).unwrap().level, 2);
+    assert!(inventory.get(3).is_none());
+    assert!(inventory.drop(0).is_none());
+    assert_eq!(inventory.items.len(), 2);
   }
 }
@@ -1,7 +1,11 @@
-use std::{fmt, str::FromStr};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 
 use crate::numeric::random_chance;
+use crate::rotf_file::{RotfBlock, RotfLine, RotfParseError};
 use crate::context::constants;
+use crate::context::data_loader::DataRow;
 use crate::context::item_loader::ItemLoader;
 
 use super::environment::Position;
@@ -9,11 +13,14 @@ use super::traits::Positionable;
 
 
 // Struct containing data about a single item
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Item {
   id: u64,
   despawn: bool,
   position: Position,
   pub view_index: i64,
+  #[serde(default)]
+  seen: bool, // whether the player has seen it, so it's only added to the bestiary once
 
   pub level: u8,
 }
@@ -40,10 +47,24 @@ impl Item {
       despawn: false,
       position: Position::FAR,
       view_index: 0,
+      seen: false,
       level,
     }
   }
 
+  pub fn id(&self) -> u64 {
+    return self.id;
+  }
+
+  // Returns true the first time the item is seen
+  pub fn mark_seen(&mut self) -> bool {
+    if self.seen {
+      return false;
+    }
+    self.seen = true;
+    return true;
+  }
+
   pub fn despawn(&self) -> bool {
     return self.despawn;
   }
@@ -85,26 +106,40 @@ impl Item {
     return format!("{} ({})", data.name, self.level);
   }
 
+  // Name followed by what a player of the given level can tell about the item
+  pub fn inspect(&self, loader: &ItemLoader, player_level: u8) -> String {
+    return self.view_short(loader) + &loader.get_data(self.id).inspect(self.level, player_level, 1.0);
+  }
+
   pub fn file_content(&self) -> String {
     let mut contents = String::new();
     contents += &format!("\n   id: {}", self.id);
     contents += &format!("\n   position: {}", self.position);
     contents += &format!("\n   view_index: {}", self.view_index);
     contents += &format!("\n   despawn: {}", self.despawn);
+    contents += &format!("\n   seen: {}", self.seen);
     contents += &format!("\n   level: {}", self.level);
     return contents;
   }
 
-  pub fn read_line(&mut self, line: String) {
-    let (key, mut value) = line.split_once(":").unwrap();
-    value = value.trim();
-    match key.trim() {
-      "id"         => self.id         = value.parse::<u64>().unwrap_or(0),
-      "position"   => self.position   = Position::from_str(value).unwrap_or(Position::FAR),
-      "view_index" => self.view_index = value.parse::<i64>().unwrap_or(-1),
-      "despawn"    => self.despawn    = value.parse::<bool>().unwrap_or(true),
-      "level"      => self.level      = value.parse::<u8>().unwrap_or(0),
-      _ => {},
+  pub fn from_block(block: &RotfBlock) -> Result<Item, RotfParseError> {
+    let mut item = Item::new(0, 0);
+    for line in &block.lines {
+      item.read_line(line)?;
     }
+    return Ok(item);
   }
-}
+
+  pub fn read_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "id"         => self.id         = line.parse::<u64>("an unsigned integer")?,
+      "position"   => self.position   = line.parse::<Position>("NEAR, MEDIUM or FAR")?,
+      "view_index" => self.view_index = line.parse::<i64>("an integer")?,
+      "despawn"    => self.despawn    = line.parse::<bool>("true or false")?,
+      "seen"       => self.seen       = line.parse::<bool>("true or false")?,
+      "level"      => self.level      = line.parse::<u8>("an integer from 0 to 255")?,
+      _ => return Err(line.unknown_key()),
+    }
+    Ok(())
+  }
+}
\ No newline at end of file
@@ -0,0 +1,199 @@
+use std::io::{Error, ErrorKind};
+
+use crate::context::constants;
+use crate::filesystem;
+use crate::paths::RotfPaths;
+
+use super::save_writer;
+
+
+// Raw contents of the files making up a saved game
+pub struct SaveFiles {
+  pub metadata: String,
+  pub player: String,
+  pub environment: String,
+}
+
+impl SaveFiles {
+  pub fn read(paths: &RotfPaths, save_name: &str) -> Result<SaveFiles, Error> {
+    return Ok(SaveFiles {
+      metadata: filesystem::read_file(paths.save_file(save_name, "metadata.rotf"))?,
+      player: filesystem::read_file(paths.save_file(save_name, "player.rotf"))?,
+      environment: filesystem::read_file(paths.save_file(save_name, "environment.rotf"))?,
+    });
+  }
+
+  pub fn write(&self, paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+    save_writer::write_save(paths, save_name, vec![
+      ("metadata.rotf", self.metadata.clone()),
+      ("player.rotf", self.player.clone()),
+      ("environment.rotf", self.environment.clone()),
+    ])
+  }
+
+  // Version of the save; saves from before versioning are version 0
+  pub fn version(&self) -> Result<u32, Error> {
+    for line in self.metadata.lines() {
+      match line.split_once(":") {
+        Some((key, value)) if key.trim() == "version" => {
+          return value.trim().parse::<u32>().map_err(|_| Error::new(ErrorKind::InvalidData,
+            format!("Invalid save version '{}'", value.trim())));
+        },
+        _ => {},
+      }
+    }
+    return Ok(0);
+  }
+
+  fn set_version(&mut self, version: u32) {
+    let mut contents = format!("\nversion: {}", version);
+    for line in self.metadata.lines() {
+      if line.is_empty() || line.trim().starts_with("version:") {
+        continue;
+      }
+      contents += &format!("\n{}", line);
+    }
+    self.metadata = contents;
+  }
+}
+
+
+// A migration upgrades a save from version n to version n + 1
+type Migration = fn(&mut SaveFiles);
+
+fn migrations() -> Vec<Migration> {
+  return vec![migrate_v0, migrate_v1];
+}
+
+// Upgrades the save step by step to the current save version
+pub fn migrate(paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+  let mut files = SaveFiles::read(paths, save_name)?;
+  let version = files.version()?;
+  if version > constants::SAVE_VERSION {
+    return Err(Error::new(ErrorKind::InvalidData, format!(
+      "Save version {} is newer than the latest supported version {}; update the game to load this save",
+      version, constants::SAVE_VERSION)));
+  }
+  if version == constants::SAVE_VERSION {
+    return Ok(());
+  }
+  for migration in migrations().iter().skip(version as usize) {
+    migration(&mut files);
+  }
+  // later versions only added keys an older save can do without
+  files.set_version(constants::SAVE_VERSION);
+  files.write(paths, save_name)
+}
+
+
+// Version 0 -> 1: write fields added with the game clock, weather, biomes and elite units
+fn migrate_v0(files: &mut SaveFiles) {
+  add_missing_keys(&mut files.environment, &[("biome", "POND"),
+    ("ticks", &constants::CLOCK_START_TICK.to_string()), ("weather", "CLEAR"), ("weather_day", "1")]);
+  add_missing_block_keys(&mut files.environment, "UNIT", &[("elite", "false"), ("announced", "false")]);
+}
+
+
+// Version 1 -> 2: move entity keys into their blocks and fix the item counter written as next_unit_key
+fn migrate_v1(files: &mut SaveFiles) {
+  move_keys_into_blocks(&mut files.player);
+  move_keys_into_blocks(&mut files.environment);
+}
+
+
+// Helper functions
+fn has_key(lines: &[&str], key: &str) -> bool {
+  return lines.iter().any(|line| match line.split_once(":") {
+    Some((k, _)) => k.trim() == key,
+    None => false,
+  });
+}
+
+// Adds keys outside of any block that are missing from the file
+fn add_missing_keys(contents: &mut String, keys: &[(&str, &str)]) {
+  let mut top_level = Vec::new();
+  let mut in_block = false;
+  for line in contents.lines() {
+    if line.trim().starts_with("%%% BEGIN") {
+      in_block = true;
+    }
+    else if line.trim().starts_with("%%% END") {
+      in_block = false;
+    }
+    else if !in_block {
+      top_level.push(line);
+    }
+  }
+  let mut missing = String::new();
+  for (key, value) in keys {
+    if !has_key(&top_level, key) {
+      missing += &format!("\n{}: {}", key, value);
+    }
+  }
+  *contents = missing + contents;
+}
+
+// Adds keys missing from each block of the given kind
+fn add_missing_block_keys(contents: &mut String, kind: &str, keys: &[(&str, &str)]) {
+  let mut new_contents = Vec::new();
+  let mut block: Option<Vec<&str>> = None;
+  for line in contents.lines() {
+    if line.trim() == format!("%%% BEGIN {}", kind) {
+      block = Some(Vec::new());
+    }
+    else if line.trim() == format!("%%% END {}", kind) {
+      match &block {
+        Some(block_lines) => {
+          for (key, value) in keys {
+            if !has_key(block_lines, key) {
+              new_contents.push(format!("   {}: {}", key, value));
+            }
+          }
+        },
+        None => {},
+      }
+      block = None;
+    }
+    else if let Some(block_lines) = block.as_mut() {
+      block_lines.push(line);
+    }
+    new_contents.push(line.to_owned());
+  }
+  *contents = new_contents.join("\n");
+}
+
+// Turns 'next_*_key' lines directly before a block into a 'key' line inside the block
+fn move_keys_into_blocks(contents: &mut String) {
+  let lines: Vec<&str> = contents.lines().collect();
+  let mut new_contents = Vec::new();
+  let mut pending_key: Option<&str> = None;
+  let mut seen_unit_counter = false; // the second unit counter is the item counter
+  for (i, line) in lines.iter().enumerate() {
+    let trimmed = line.trim();
+    if trimmed.starts_with("%%% BEGIN") {
+      new_contents.push(line.to_string());
+      match pending_key.take() {
+        Some(key) => new_contents.push(format!("   key: {}", key)),
+        None => {},
+      }
+      continue;
+    }
+    match trimmed.split_once(":") {
+      Some((key, value)) if key.trim() == "next_unit_key" || key.trim() == "next_item_key" => {
+        let next_line = lines[i + 1..].iter().map(|l| l.trim()).find(|l| !l.is_empty());
+        if next_line.map_or(false, |l| l.starts_with("%%% BEGIN")) {
+          pending_key = Some(value.trim());
+        }
+        else if key.trim() == "next_unit_key" && seen_unit_counter {
+          new_contents.push(format!("next_item_key: {}", value.trim()));
+        }
+        else {
+          seen_unit_counter |= key.trim() == "next_unit_key";
+          new_contents.push(line.to_string());
+        }
+      },
+      _ => new_contents.push(line.to_string()),
+    }
+  }
+  *contents = new_contents.join("\n");
+}
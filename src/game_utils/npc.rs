@@ -0,0 +1,76 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rotf_file::{RotfBlock, RotfLine, RotfParseError};
+
+use super::environment::Position;
+use super::traits::Positionable;
+
+
+// Non-hostile character the player can talk to; what it says is in its dialogue file
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Npc {
+  dialogue: String, // id of the dialogue file, like OLD_TOAD
+  pub name: String,
+  position: Position,
+  pub view_index: i64,
+}
+
+impl fmt::Display for Npc {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "NPC: {}", self.name)
+  }
+}
+
+impl Positionable for Npc {
+  fn position(&self) -> Position {
+    return self.position.clone();
+  }
+  fn set_position(&mut self, position: Position) {
+    self.position = position;
+  }
+}
+
+impl Npc {
+  pub fn new(dialogue: String, name: String) -> Npc {
+    return Npc {
+      dialogue,
+      name,
+      position: Position::FAR,
+      view_index: 0,
+    }
+  }
+
+  pub fn dialogue(&self) -> &str {
+    return &self.dialogue;
+  }
+
+  pub fn file_content(&self) -> String {
+    let mut contents = String::new();
+    contents += &format!("\n   dialogue: {}", self.dialogue);
+    contents += &format!("\n   name: {}", self.name);
+    contents += &format!("\n   position: {}", self.position);
+    contents += &format!("\n   view_index: {}", self.view_index);
+    return contents;
+  }
+
+  pub fn from_block(block: &RotfBlock) -> Result<Npc, RotfParseError> {
+    let mut npc = Npc::new("".to_owned(), "".to_owned());
+    for line in &block.lines {
+      npc.read_line(line)?;
+    }
+    return Ok(npc);
+  }
+
+  pub fn read_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "dialogue"   => self.dialogue   = line.value.clone(),
+      "name"       => self.name       = line.value.clone(),
+      "position"   => self.position   = line.parse::<Position>("NEAR, MEDIUM or FAR")?,
+      "view_index" => self.view_index = line.parse::<i64>("an integer")?,
+      _ => return Err(line.unknown_key()),
+    }
+    Ok(())
+  }
+}
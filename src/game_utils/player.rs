@@ -1,20 +1,29 @@
-use std::io::{Error, BufRead};
-use std::str::FromStr;
+use std::cmp::max;
+use std::io::Error;
 use std::collections::HashSet;
 
-use crate::{commands::Command, filesystem};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::Command;
+use crate::numeric::IntegerRange;
+use crate::paths::RotfPaths;
+use crate::rotf_file::{self, RotfEntry, RotfLine, RotfParseError};
 
 use super::environment::Position;
 use super::traits::Positionable;
 use super::item::Item;
 use super::inventory::Inventory;
 use super::ability::Ability;
+use super::bestiary::Bestiary;
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RotfPlayer {
   pub level: u8,
   pub view_distance: Position,
   pub inventory: Inventory,
   pub abilities: HashSet<Ability>,
+  #[serde(default = "Bestiary::new")]
+  pub bestiary: Bestiary,
 }
 
 impl RotfPlayer {
@@ -26,12 +35,14 @@ impl RotfPlayer {
       view_distance: Position::NEAR,
       inventory: Inventory::new(),
       abilities,
+      bestiary: Bestiary::new(),
     }
   }
 
   pub fn environment_commands(&self) -> Vec<Command> {
     return vec![Command::VIEW, Command::WAIT, Command::FIGHT, Command::PICKUP,
-      Command::INVENTORY, Command::DROP];
+      Command::INVENTORY, Command::DROP, Command::INSPECT, Command::TALK,
+      Command::TRAVEL];
   }
 
   pub fn combat_commands(&self) -> Vec<Command> {
@@ -46,6 +57,12 @@ impl RotfPlayer {
     return 1 + self.level / 10;
   }
 
+  // Levels a player in the given tier can be
+  pub fn tier_levels(tier: u8) -> IntegerRange {
+    let start = (tier as i64 - 1) * 10;
+    return IntegerRange::between(start, start + 9);
+  }
+
   pub fn me(&self, name: String) -> String {
     let mut str = String::new();
     str += "Player Info";
@@ -63,8 +80,8 @@ impl RotfPlayer {
     contents += "\n";
     contents += &format!("\ncapacity: {}", self.inventory.capacity.clone());
     for (key, item) in &self.inventory.items {
-      contents += &format!("\nnext_item_key: {}", key.clone());
       contents += "\n%%% BEGIN ITEM";
+      contents += &format!("\n   key: {}", key);
       contents += &item.file_content();
       contents += "\n%%% END ITEM\n";
     }
@@ -72,49 +89,41 @@ impl RotfPlayer {
     for ability in &self.abilities {
       contents += &format!("\nability: {}", ability);
     }
+    // bestiary
+    contents += "\n";
+    contents += &self.bestiary.file_content();
     return contents;
   }
 
-  pub fn load(&mut self, save_name: String) -> Result<(), Error> {
-    let mut in_item = false;
-    let mut curr_item = Item::new(0, 0);
-    for oline in filesystem::open_file(format!("data/saves/{}/player.rotf", save_name))?.lines() {
-      let line = oline?;
-      match line.trim() {
-        "%%% BEGIN ITEM" => {
-          in_item = true;
-        }
-        "%%% END ITEM" => {
-          in_item = false;
-          self.inventory.add(curr_item);
-          curr_item = Item::new(0, 0);
-        }
-        _ => {},
-      }
-      if !line.clone().contains(":") {
-        continue;
+  pub fn load(&mut self, paths: &RotfPaths, save_name: String) -> Result<(), Error> {
+    for entry in rotf_file::parse_file(paths.save_file(&save_name, "player.rotf"))? {
+      match entry {
+        RotfEntry::Block(mut block) => match block.kind.as_str() {
+          "ITEM" => {
+            let key = block.take("key")?.parse::<u64>("an unsigned integer")?;
+            self.inventory.insert(key, Item::from_block(&block)?);
+          },
+          _ => self.bestiary.read_block(&mut block)?,
+        },
+        RotfEntry::Line(line) => self.read_line(&line)?,
       }
-      if in_item {
-        curr_item.read_line(line);
-        continue;
-      }
-      let (key, mut value) = line.split_once(":").unwrap();
-      value = value.trim();
-      self.read_line(key.trim(), value);
     }
     Ok(())
   }
 
-  pub fn read_line(&mut self, key: &str, value: &str) {
-    match key {
-      "level" => self.level = value.parse::<u8>().unwrap_or(0),
-      "view_distance" => self.view_distance = Position::from_str(value).unwrap_or(Position::FAR),
-      "capacity" => self.inventory.capacity = value.parse::<usize>().unwrap_or(0),
-      "next_item_key" => self.inventory.next_item_key = value.parse::<u64>().unwrap_or(1),
+  pub fn read_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "level" => self.level = line.parse::<u8>("an integer from 0 to 255")?,
+      "view_distance" => self.view_distance = line.parse::<Position>("NEAR, MEDIUM or FAR")?,
+      "capacity" => self.inventory.capacity = line.parse::<usize>("an unsigned integer")?,
+      "next_item_key" => {
+        self.inventory.next_item_key = max(self.inventory.next_item_key, line.parse::<u64>("an unsigned integer")?);
+      },
       "ability" => {
-        self.abilities.insert(Ability::from_str(value).unwrap_or(Ability::NOTHING));
+        self.abilities.insert(line.parse::<Ability>("an ability")?);
       },
-      _ => {},
+      _ => return Err(line.unknown_key()),
     }
+    Ok(())
   }
-}
+}
\ No newline at end of file
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::context::constants;
+use crate::filesystem;
+use crate::paths::RotfPaths;
+use crate::rotf_file::{self, RotfEntry};
+
+use super::{RotfGame, RotfDifficulty, migration, save_writer};
+
+
+// Format games are written in
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum SaveFormat {
+  ROTF, // metadata, player and environment .rotf files
+  JSON, // single structured save.json file
+}
+
+impl fmt::Display for SaveFormat {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for SaveFormat {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for format in SaveFormat::iter() {
+      if format.to_string() == s.to_uppercase() {
+        return Ok(format);
+      }
+    }
+    Err(())
+  }
+}
+
+impl SaveFormat {
+  pub fn default() -> SaveFormat {
+    return SaveFormat::ROTF;
+  }
+
+  // Format of an existing save
+  pub fn detect(paths: &RotfPaths, save_name: &str) -> SaveFormat {
+    return SaveFormat::detect_folder(&paths.save(save_name));
+  }
+
+  // Format of a save or backup folder
+  pub fn detect_folder(folder: &str) -> SaveFormat {
+    if Path::new(&format!("{}/save.json", folder)).exists() {
+      return SaveFormat::JSON;
+    }
+    return SaveFormat::ROTF;
+  }
+
+  pub fn backend(&self) -> Box<dyn SaveBackend> {
+    match self {
+      SaveFormat::ROTF => Box::new(RotfBackend {}),
+      SaveFormat::JSON => Box::new(JsonBackend {}),
+    }
+  }
+}
+
+
+// Reads and writes games in a save folder
+pub trait SaveBackend {
+  fn save(&self, paths: &RotfPaths, game: &RotfGame) -> Result<(), Error>;
+  fn load(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error>;
+  // Name of the saved game without loading all of it
+  fn display_name(&self, paths: &RotfPaths, save_name: &str) -> Result<String, Error>;
+  // Metadata and player of the saved game, read as they are without migrating the save
+  fn peek(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error>;
+  // Rewrites only the name of the game in a save or backup folder
+  fn set_name(&self, folder: &str, name: &str) -> Result<(), Error>;
+}
+
+
+// Legacy save folder with metadata, player and environment .rotf files
+pub struct RotfBackend {}
+
+impl SaveBackend for RotfBackend {
+  fn save(&self, paths: &RotfPaths, game: &RotfGame) -> Result<(), Error> {
+    save_writer::write_save(paths, &game.slot, vec![
+      ("metadata.rotf", game.metadata_content()),
+      ("player.rotf", game.player.file_content()),
+      ("environment.rotf", game.environment.file_content()),
+    ])
+  }
+
+  fn load(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error> {
+    // upgrade older save formats
+    migration::migrate(paths, save_name)?;
+    let mut game = self.peek(paths, save_name)?;
+    // load environment
+    game.environment.load(paths, save_name.to_owned())?;
+    Ok(game)
+  }
+
+  fn peek(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error> {
+    let mut game = RotfGame::new(save_name.to_owned(), RotfDifficulty::default());
+    // load metadata
+    for entry in rotf_file::parse_file(paths.save_file(save_name, "metadata.rotf"))? {
+      match entry {
+        RotfEntry::Block(block) => return Err(block.unknown_kind().into()),
+        RotfEntry::Line(line) => game.read_metadata_line(&line)?,
+      }
+    }
+    // load player
+    game.player.load(paths, save_name.to_owned())?;
+    Ok(game)
+  }
+
+  fn set_name(&self, folder: &str, name: &str) -> Result<(), Error> {
+    let file = format!("{}/metadata.rotf", folder);
+    let metadata: Vec<String> = filesystem::read_file(file.clone())?.lines().map(|line| match line.split_once(":") {
+      Some((key, _)) if key.trim() == "name" => format!("name: {}", name),
+      _ => line.to_owned(),
+    }).collect();
+    filesystem::create_file_synced(file, metadata.join("\n"))
+  }
+
+  fn display_name(&self, paths: &RotfPaths, save_name: &str) -> Result<String, Error> {
+    for entry in rotf_file::parse_file(paths.save_file(save_name, "metadata.rotf"))? {
+      match entry {
+        RotfEntry::Line(line) if line.key == "name" => return Ok(line.value),
+        _ => {},
+      }
+    }
+    return Err(Error::new(ErrorKind::InvalidData, format!("{} has no name", save_name)));
+  }
+}
+
+
+// Save folder with a single save.json file
+pub struct JsonBackend {}
+
+#[derive(Serialize)]
+struct JsonSaveRef<'a> {
+  version: u32,
+  game: &'a RotfGame,
+}
+
+#[derive(Deserialize)]
+struct JsonSave {
+  version: u32,
+  game: RotfGame,
+}
+
+impl JsonBackend {
+  pub fn to_json(game: &RotfGame) -> Result<String, Error> {
+    let save = JsonSaveRef {
+      version: constants::SAVE_VERSION,
+      game,
+    };
+    return serde_json::to_string_pretty(&save).map_err(|e| Error::new(ErrorKind::InvalidData, e));
+  }
+
+  pub fn from_json(file: &str, contents: &str) -> Result<RotfGame, Error> {
+    let save: JsonSave = serde_json::from_str(contents).map_err(|e| Error::new(ErrorKind::InvalidData,
+      format!("{} line {} column {}: {}", file, e.line(), e.column(), e)))?;
+    if save.version > constants::SAVE_VERSION {
+      return Err(Error::new(ErrorKind::InvalidData, format!(
+        "Save version {} is newer than the latest supported version {}; update the game to load this save",
+        save.version, constants::SAVE_VERSION)));
+    }
+    return Ok(save.game);
+  }
+}
+
+impl SaveBackend for JsonBackend {
+  fn save(&self, paths: &RotfPaths, game: &RotfGame) -> Result<(), Error> {
+    save_writer::write_save(paths, &game.slot, vec![("save.json", JsonBackend::to_json(game)?)])
+  }
+
+  fn load(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error> {
+    let file = paths.save_file(save_name, "save.json");
+    let mut game = JsonBackend::from_json(&file, &filesystem::read_file(file.clone())?)?;
+    game.save_format = SaveFormat::JSON;
+    return Ok(game);
+  }
+
+  fn display_name(&self, paths: &RotfPaths, save_name: &str) -> Result<String, Error> {
+    let file = paths.save_file(save_name, "save.json");
+    let save: serde_json::Value = serde_json::from_str(&filesystem::read_file(file)?)
+      .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    match save["game"]["name"].as_str() {
+      Some(name) => Ok(name.to_owned()),
+      None => Err(Error::new(ErrorKind::InvalidData, format!("{} has no name", save_name))),
+    }
+  }
+
+  // JSON saves are not migrated so loading them is already read only
+  fn peek(&self, paths: &RotfPaths, save_name: &str) -> Result<RotfGame, Error> {
+    return self.load(paths, save_name);
+  }
+
+  fn set_name(&self, folder: &str, name: &str) -> Result<(), Error> {
+    let file = format!("{}/save.json", folder);
+    let mut save: serde_json::Value = serde_json::from_str(&filesystem::read_file(file.clone())?)
+      .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    if !save["game"].is_object() {
+      return Err(Error::new(ErrorKind::InvalidData, format!("{} has no game", file)));
+    }
+    save["game"]["name"] = serde_json::Value::from(name);
+    let contents = serde_json::to_string_pretty(&save).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    filesystem::create_file_synced(file, contents)
+  }
+}
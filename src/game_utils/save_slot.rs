@@ -0,0 +1,141 @@
+use std::io::Error;
+
+use crate::filesystem;
+use crate::paths::RotfPaths;
+
+use super::save_backend::SaveFormat;
+use super::save_writer;
+
+
+// A saved game on disk; the id is its folder and the name is read from its metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveSlot {
+  pub id: String,
+  pub name: String,
+}
+
+impl SaveSlot {
+  // Name with the slot id when it can't be derived from the name
+  pub fn label(&self) -> String {
+    if slot_id(&self.name) == self.id {
+      return self.name.clone();
+    }
+    return format!("{} [{}]", self.name, self.id);
+  }
+}
+
+
+// Lossless folder name for a display name: lowercase letters, digits and '.' are kept,
+// spaces become '_' and every other character is written as %XX escapes of its utf-8 bytes
+pub fn slot_id(name: &str) -> String {
+  let mut id = String::new();
+  for c in name.chars() {
+    match c {
+      'a'..='z' | '0'..='9' | '.' => id.push(c),
+      ' ' => id.push('_'),
+      _ => {
+        let mut bytes = [0; 4];
+        for b in c.encode_utf8(&mut bytes).bytes() {
+          id += &format!("%{:02X}", b);
+        }
+      },
+    }
+  }
+  return id;
+}
+
+// Display name a slot id was encoded from
+pub fn decode(id: &str) -> Option<String> {
+  let mut bytes = Vec::new();
+  let mut chars = id.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '_' => bytes.push(b' '),
+      '%' => {
+        let hex: String = chars.by_ref().take(2).collect();
+        bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+      },
+      _ => {
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+      },
+    }
+  }
+  return String::from_utf8(bytes).ok();
+}
+
+// All saved games, skipping hidden folders like the backups and trash
+pub fn list(paths: &RotfPaths) -> Result<Vec<SaveSlot>, Error> {
+  let mut slots = Vec::new();
+  save_writer::recover_interrupted(paths)?;
+  for entry in filesystem::open_folder_or_create(paths.saves())? {
+    let id = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if !entry.is_dir() || id.starts_with(".") {
+      continue;
+    }
+    let name = match SaveFormat::detect(paths, &id).backend().display_name(paths, &id) {
+      Ok(name) => name,
+      Err(_) => decode(&id).unwrap_or(id.clone()),
+    };
+    slots.push(SaveSlot { id, name });
+  }
+  return Ok(slots);
+}
+
+// Finds a saved game by slot id, then display name, then display name ignoring case when only one matches
+pub fn find(paths: &RotfPaths, query: &str) -> Result<Option<SaveSlot>, Error> {
+  let query = query.trim();
+  let slots = list(paths)?;
+  if let Some(slot) = slots.iter().find(|s| s.id == query).or_else(|| slots.iter().find(|s| s.name == query)) {
+    return Ok(Some(slot.clone()));
+  }
+  let matches: Vec<&SaveSlot> = slots.iter().filter(|s| s.name.to_lowercase() == query.to_lowercase()).collect();
+  if matches.len() == 1 {
+    return Ok(Some(matches[0].clone()));
+  }
+  return Ok(None);
+}
+
+// Moves a saved game and its backups to the slot of a new name, then rewrites the name they hold
+pub fn rename(paths: &RotfPaths, slot: &SaveSlot, name: &str) -> Result<SaveSlot, Error> {
+  let renamed = SaveSlot { id: slot_id(name), name: name.to_owned() };
+  save_writer::move_save(paths, &slot.id, &renamed.id)?;
+  let mut folders = vec![paths.save(&renamed.id)];
+  for generation in save_writer::generations(paths, &renamed.id) {
+    folders.push(save_writer::backup_path(paths, &renamed.id, generation));
+  }
+  for folder in folders {
+    SaveFormat::detect_folder(&folder).backend().set_name(&folder, name)?;
+  }
+  return Ok(renamed);
+}
+
+// Whether a new game with the given display name would clash with an existing saved game
+pub fn name_taken(paths: &RotfPaths, name: &str) -> Result<bool, Error> {
+  let id = slot_id(name);
+  return Ok(list(paths)?.iter().any(|s| s.id == id || s.name.to_lowercase() == name.to_lowercase()));
+}
+
+
+#[cfg(test)]
+pub mod test_save_slot {
+  use rstest::*;
+  use crate::game::save_slot::*;
+
+  #[rstest]
+  #[case::lowercase("test", "test")]
+  #[case::spaces("my game", "my_game")]
+  #[case::underscore("my_game", "my%5Fgame")]
+  #[case::uppercase("My Game", "%4Dy_%47ame")]
+  #[case::unicode("frög", "fr%C3%B6g")]
+  fn test_slot_id(#[case] name: &str, #[case] id: &str) {
+    assert_eq!(slot_id(name), id);
+    assert_eq!(decode(id), Some(name.to_owned()));
+  }
+
+  #[test]
+  fn test_decode_invalid() {
+    assert_eq!(decode("bad%zz"), None);
+    assert_eq!(decode("bad%C3"), None);
+  }
+}
@@ -0,0 +1,184 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::context::constants;
+use crate::filesystem;
+use crate::paths::RotfPaths;
+use crate::rotf_file;
+
+
+// Folder a save is written to before it is swapped in
+fn temp_root(paths: &RotfPaths) -> String {
+  return format!("{}/.tmp", paths.saves());
+}
+
+fn temp_path(paths: &RotfPaths, save_name: &str) -> String {
+  return format!("{}/{}", temp_root(paths), save_name);
+}
+
+pub fn backups_path(paths: &RotfPaths, save_name: &str) -> String {
+  return format!("{}/.backups/{}", paths.saves(), save_name);
+}
+
+// Generation 1 is the most recent backup
+pub fn backup_path(paths: &RotfPaths, save_name: &str, generation: usize) -> String {
+  return format!("{}/{}", backups_path(paths, save_name), generation);
+}
+
+// Files holding the save metadata; written last so a temporary save that has one is complete
+const METADATA_FILES: [&str; 2] = ["metadata.rotf", "save.json"];
+
+// Writes all files of a save to a temporary folder then swaps it in
+pub fn write_save(paths: &RotfPaths, save_name: &str, mut files: Vec<(&str, String)>) -> Result<(), Error> {
+  let temp = temp_path(paths, save_name);
+  if Path::new(&temp).exists() {
+    filesystem::delete_folder(temp.clone())?;
+  }
+  filesystem::create_folder(temp.clone())?;
+  files.sort_by_key(|(file_name, _)| METADATA_FILES.contains(file_name));
+  for (file_name, contents) in files {
+    filesystem::create_file_synced(format!("{}/{}", temp, file_name), contents)?;
+  }
+  swap_in(paths, save_name)
+}
+
+// Moves the current save into the backups then the temporary folder into its place
+fn swap_in(paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+  let current = paths.save(save_name);
+  filesystem::sync_folder(temp_path(paths, save_name))?;
+  filesystem::sync(temp_root(paths))?;
+  if Path::new(&current).exists() {
+    rotate_backups(paths, save_name)?;
+    filesystem::rename(current.clone(), backup_path(paths, save_name, 1))?;
+  }
+  filesystem::rename(temp_path(paths, save_name), current)?;
+  filesystem::sync(paths.saves())
+}
+
+// Whether a temporary save was fully written before being interrupted
+fn is_complete(folder: &str) -> bool {
+  let metadata = format!("{}/{}", folder, METADATA_FILES[0]);
+  if Path::new(&metadata).exists() {
+    return rotf_file::parse_file(metadata).is_ok();
+  }
+  return Path::new(&format!("{}/{}", folder, METADATA_FILES[1])).exists();
+}
+
+fn rotate_backups(paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+  filesystem::create_folder(backups_path(paths, save_name))?;
+  let oldest = backup_path(paths, save_name, constants::SAVE_BACKUP_GENERATIONS);
+  if Path::new(&oldest).exists() {
+    filesystem::delete_folder(oldest)?;
+  }
+  for generation in (1..constants::SAVE_BACKUP_GENERATIONS).rev() {
+    let backup = backup_path(paths, save_name, generation);
+    if Path::new(&backup).exists() {
+      filesystem::rename(backup, backup_path(paths, save_name, generation + 1))?;
+    }
+  }
+  Ok(())
+}
+
+// Backup generations available for a save
+pub fn generations(paths: &RotfPaths, save_name: &str) -> Vec<usize> {
+  let mut generations = Vec::new();
+  for generation in 1..=constants::SAVE_BACKUP_GENERATIONS {
+    if Path::new(&backup_path(paths, save_name, generation)).exists() {
+      generations.push(generation);
+    }
+  }
+  return generations;
+}
+
+// Replaces a save with one of its backups; the replaced save becomes the newest backup
+pub fn restore(paths: &RotfPaths, save_name: &str, generation: usize) -> Result<(), Error> {
+  let backup = backup_path(paths, save_name, generation);
+  if !Path::new(&backup).exists() {
+    return Err(Error::new(ErrorKind::NotFound,
+      format!("No backup generation {} for {}", generation, save_name)));
+  }
+  let temp = temp_path(paths, save_name);
+  if Path::new(&temp).exists() {
+    filesystem::delete_folder(temp.clone())?;
+  }
+  filesystem::copy_folder(backup, temp)?;
+  swap_in(paths, save_name)
+}
+
+pub fn delete_backups(paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+  let backups = backups_path(paths, save_name);
+  if Path::new(&backups).exists() {
+    filesystem::delete_folder(backups)?;
+  }
+  Ok(())
+}
+
+// Moves a save and its backups to another slot, replacing backups left over in that slot
+pub fn move_save(paths: &RotfPaths, save_name: &str, new_save_name: &str) -> Result<(), Error> {
+  delete_backups(paths, new_save_name)?;
+  filesystem::rename(paths.save(save_name), paths.save(new_save_name))?;
+  let backups = backups_path(paths, save_name);
+  if Path::new(&backups).exists() {
+    filesystem::rename(backups, backups_path(paths, new_save_name))?;
+  }
+  filesystem::sync(paths.saves())
+}
+
+// Finishes swapping in saves that were interrupted after the current save was moved to the backups,
+// temporary saves that were still being written are dropped
+pub fn recover_interrupted(paths: &RotfPaths) -> Result<(), Error> {
+  if !Path::new(&temp_root(paths)).exists() {
+    return Ok(());
+  }
+  for entry in filesystem::open_folder(temp_root(paths))? {
+    let save_name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let temp = temp_path(paths, &save_name);
+    if !is_complete(&temp) {
+      filesystem::delete_folder(temp)?;
+    }
+    else if !Path::new(&paths.save(&save_name)).exists() {
+      filesystem::rename(temp, paths.save(&save_name))?;
+    }
+  }
+  Ok(())
+}
+
+
+#[cfg(test)]
+pub mod test_save_writer {
+  use std::path::Path;
+
+  use crate::filesystem;
+  use crate::game::save_writer::*;
+  use crate::test_main::TempRoot;
+
+  #[test]
+  fn test_write_save() {
+    let temp = TempRoot::saves("save_writer");
+    let paths = &temp.paths;
+    write_save(paths, "slot", vec![
+      ("metadata.rotf", "\nversion: 1".to_owned()),
+      ("player.rotf", "\nname: frog".to_owned()),
+    ]).unwrap();
+    assert_eq!(filesystem::read_file(paths.save_file("slot", "player.rotf")).unwrap(), "\nname: frog");
+    assert!(!Path::new(&paths.save_file("slot", "player.rotf.part")).exists());
+    assert!(!Path::new(&format!("{}/.tmp/slot", paths.saves())).exists());
+  }
+
+  #[test]
+  fn test_recover_interrupted() {
+    let temp = TempRoot::saves("recover");
+    let (root, paths) = (temp.root(), &temp.paths);
+    // interrupted before the metadata was written
+    filesystem::create_folder(format!("{}/.tmp/partial", root)).unwrap();
+    filesystem::create_file(format!("{}/.tmp/partial/player.rotf", root), "\nname: frog".to_owned()).unwrap();
+    // interrupted after the old save was moved to the backups
+    filesystem::create_folder(format!("{}/.tmp/complete", root)).unwrap();
+    filesystem::create_file(format!("{}/.tmp/complete/player.rotf", root), "\nname: frog".to_owned()).unwrap();
+    filesystem::create_file(format!("{}/.tmp/complete/metadata.rotf", root), "\nversion: 1".to_owned()).unwrap();
+    recover_interrupted(paths).unwrap();
+    assert!(!Path::new(&paths.save("partial")).exists());
+    assert!(!Path::new(&format!("{}/.tmp/partial", root)).exists());
+    assert!(Path::new(&paths.save_file("complete", "metadata.rotf")).exists());
+  }
+}
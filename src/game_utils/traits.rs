@@ -2,6 +2,7 @@ use crate::numeric::random_int;
 
 use super::environment::Position;
 use super::ability::Ability;
+use super::weather::Weather;
 
 
 // Trait for position
@@ -70,9 +71,16 @@ pub enum DamageType {
 
 // Trait for combat
 pub trait Combatable : Damageable {
-  fn use_ability(&mut self, ability: Ability, target: &mut dyn Damageable) {
+  fn use_ability(&mut self, ability: Ability, target: &mut dyn Damageable, weather: &Weather) {
+    let damage = self.damage_dealt(ability.get_stats(), ability.minimum_damage(), target, weather);
+    target.damage(damage);
+  }
+
+  // Damage of an ability with the given extras, factors and type against the target in the weather
+  fn damage_dealt(&self, stats: (CombatStats, CombatStats, DamageType), minimum_damage: f64,
+    target: &dyn Damageable, weather: &Weather) -> f64 {
     // get attacker's stats
-    let (ability_extras, ability_factors, damage_type) = ability.get_stats();
+    let (ability_extras, ability_factors, damage_type) = stats;
     let attack = (ability_extras.attack + self.attack()) * ability_factors.attack;
     let magic = (ability_extras.magic + self.magic()) * ability_factors.magic;
     let piercing = (ability_extras.piercing + self.piercing()) * ability_factors.piercing;
@@ -95,16 +103,93 @@ pub trait Combatable : Damageable {
       DamageType::TRUE => {},
     }
     // calculate damage
-    let mut damage = attack + magic - effective_defense;
-    if damage < ability.minimum_damage() {
-      damage = ability.minimum_damage();
+    let mut damage = (attack + magic - effective_defense) * weather.damage_factor(&damage_type);
+    if damage < minimum_damage {
+      damage = minimum_damage;
     }
-    // apply damage
-    target.damage(damage);
+    return damage;
   }
 
   fn attack(&self) -> f64;
   fn magic(&self) -> f64;
   fn piercing(&self) -> f64;
   fn penetration(&self) -> f64;
+}
+
+
+#[cfg(test)]
+pub mod test_traits {
+  use crate::game::traits::*;
+  use crate::game::weather::Weather;
+
+  // Fighter with fixed stats that records the damage it takes
+  struct TestFighter {
+    attack: f64,
+    defense: f64,
+    resistance: f64,
+    damage_taken: f64,
+  }
+
+  impl Damageable for TestFighter {
+    fn damage(&mut self, amount: f64) {
+      self.damage_taken += amount;
+    }
+    fn heal(&mut self, amount: f64) {
+      self.damage_taken -= amount;
+    }
+    fn health(&self) -> f64 {
+      return 100.0 - self.damage_taken;
+    }
+    fn max_health(&self) -> f64 {
+      return 100.0;
+    }
+    fn defense(&self) -> f64 {
+      return self.defense;
+    }
+    fn resistance(&self) -> f64 {
+      return self.resistance;
+    }
+  }
+
+  impl Combatable for TestFighter {
+    fn attack(&self) -> f64 {
+      return self.attack;
+    }
+    fn magic(&self) -> f64 {
+      return 0.0;
+    }
+    fn piercing(&self) -> f64 {
+      return 0.0;
+    }
+    fn penetration(&self) -> f64 {
+      return 0.0;
+    }
+  }
+
+  fn fighter(attack: f64, defense: f64) -> TestFighter {
+    return TestFighter { attack, defense, resistance: 0.0, damage_taken: 0.0 };
+  }
+
+  // Ability that adds nothing to the attacker and uses its full attack
+  fn full_attack(damage_type: DamageType) -> (CombatStats, CombatStats, DamageType) {
+    let mut factors = CombatStats::new();
+    factors.attack = 1.0;
+    factors.magic = 1.0;
+    factors.piercing = 1.0;
+    factors.penetration = 1.0;
+    return (CombatStats::new(), factors, damage_type);
+  }
+
+  #[test]
+  fn test_damage_dealt_uses_weather() {
+    let attacker = fighter(20.0, 0.0);
+    let target = fighter(0.0, 10.0);
+    // piercing is 0, so physical damage is reduced by the full defense
+    assert_eq!(attacker.damage_dealt(full_attack(DamageType::PHYSICAL), 0.0, &target, &Weather::CLEAR), 10.0);
+    assert_eq!(attacker.damage_dealt(full_attack(DamageType::PHYSICAL), 0.0, &target, &Weather::RAIN), 9.0);
+    assert_eq!(attacker.damage_dealt(full_attack(DamageType::PHYSICAL), 0.0, &target, &Weather::DROUGHT), 11.0);
+    assert_eq!(attacker.damage_dealt(full_attack(DamageType::TRUE), 0.0, &target, &Weather::COLD_SNAP), 20.0);
+    // the minimum damage applies after the weather
+    assert_eq!(attacker.damage_dealt(full_attack(DamageType::PHYSICAL), 10.0, &target, &Weather::COLD_SNAP), 10.0);
+  }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::filesystem;
+use crate::paths::RotfPaths;
+
+use super::save_writer::backups_path;
+
+
+// Deleted saves are kept in numbered entries of the trash, where higher numbers are more recent
+fn trash_root(paths: &RotfPaths) -> String {
+  return format!("{}/.trash", paths.saves());
+}
+
+// Save names can't contain '-', so it separates the number from the name
+fn entry_path(paths: &RotfPaths, number: usize, save_name: &str) -> String {
+  return format!("{}/{}-{}", trash_root(paths), number, save_name);
+}
+
+// Entries in the trash as (number, save name), oldest first
+pub fn entries(paths: &RotfPaths) -> Result<Vec<(usize, String)>, Error> {
+  let mut entries = Vec::new();
+  if !Path::new(&trash_root(paths)).exists() {
+    return Ok(entries);
+  }
+  for entry in filesystem::open_folder(trash_root(paths))? {
+    let name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+    match name.split_once("-") {
+      Some((number, save_name)) => match number.parse::<usize>() {
+        Ok(n) => entries.push((n, save_name.to_owned())),
+        Err(_) => {},
+      },
+      None => {},
+    }
+  }
+  entries.sort();
+  return Ok(entries);
+}
+
+// Moves a save and its backups into the trash then purges the oldest entries beyond the kept count
+pub fn trash_save(paths: &RotfPaths, save_name: &str, keep: usize) -> Result<(), Error> {
+  let number = entries(paths)?.last().map(|(n, _)| n + 1).unwrap_or(1);
+  let entry = entry_path(paths, number, save_name);
+  filesystem::create_folder(entry.clone())?;
+  filesystem::rename(paths.save(save_name), format!("{}/save", entry))?;
+  let backups = backups_path(paths, save_name);
+  if Path::new(&backups).exists() {
+    filesystem::rename(backups, format!("{}/backups", entry))?;
+  }
+  purge(paths, keep)
+}
+
+// Moves the most recently deleted save with the given name back out of the trash
+pub fn undelete(paths: &RotfPaths, save_name: &str) -> Result<(), Error> {
+  let number = match entries(paths)?.into_iter().rev().find(|(_, name)| name == save_name) {
+    Some((n, _)) => n,
+    None => return Err(Error::new(ErrorKind::NotFound,
+      format!("{} is not in the trash", save_name))),
+  };
+  if Path::new(&paths.save(save_name)).exists() {
+    return Err(Error::new(ErrorKind::AlreadyExists,
+      format!("A saved game named {} already exists", save_name)));
+  }
+  let entry = entry_path(paths, number, save_name);
+  filesystem::rename(format!("{}/save", entry), paths.save(save_name))?;
+  let backups = format!("{}/backups", entry);
+  if Path::new(&backups).exists() {
+    let current_backups = backups_path(paths, save_name);
+    if Path::new(&current_backups).exists() {
+      filesystem::delete_folder(current_backups.clone())?;
+    }
+    filesystem::create_folder(format!("{}/.backups", paths.saves()))?;
+    filesystem::rename(backups, current_backups)?;
+  }
+  filesystem::delete_folder(entry)
+}
+
+// Permanently deletes the oldest entries so at most the kept count remain
+pub fn purge(paths: &RotfPaths, keep: usize) -> Result<(), Error> {
+  let entries = entries(paths)?;
+  if entries.len() <= keep {
+    return Ok(());
+  }
+  for (number, save_name) in &entries[..entries.len() - keep] {
+    match filesystem::delete_folder(entry_path(paths, *number, save_name)) {
+      Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+      _ => {},
+    }
+  }
+  Ok(())
+}
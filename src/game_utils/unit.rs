@@ -1,7 +1,11 @@
-use std::{fmt, str::FromStr};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 
 use crate::numeric::random_chance;
+use crate::rotf_file::{RotfBlock, RotfLine, RotfParseError};
 use crate::context::constants;
+use crate::context::data_loader::DataRow;
 use crate::context::unit_loader::UnitLoader;
 
 use super::ability::Ability;
@@ -10,11 +14,16 @@ use super::traits::Positionable;
 
 
 // Struct containing data about a single AI unit
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Unit {
   id: u64,
   despawn: bool,
   position: Position,
   pub view_index: i64,
+  #[serde(default)]
+  seen: bool, // whether the player has seen it, so it's only added to the bestiary once
+  elite: bool,
+  announced: bool, // whether an elite unit has been announced to the player
 
   pub level: u8,
 }
@@ -41,24 +50,68 @@ impl Unit {
       despawn: false,
       position: Position::FAR,
       view_index: 0,
+      seen: false,
+      elite: false,
+      announced: false,
       level,
     }
   }
 
+  pub fn id(&self) -> u64 {
+    return self.id;
+  }
+
+  // Randomly make this unit elite, boosting its level and stats
+  pub fn roll_elite(&mut self) {
+    if !random_chance(constants::ELITE_SPAWN_CHANCE) {
+      return;
+    }
+    self.elite = true;
+    self.level = self.level.saturating_add(constants::ELITE_LEVEL_BONUS);
+  }
+
+  // Returns true the first time an elite unit is announced
+  pub fn announce(&mut self) -> bool {
+    if !self.elite || self.announced {
+      return false;
+    }
+    self.announced = true;
+    return true;
+  }
+
+  pub fn stat_factor(&self) -> f64 {
+    if self.elite {
+      return constants::ELITE_STAT_FACTOR;
+    }
+    return 1.0;
+  }
+
   pub fn abilities(&self) -> Vec<Ability> {
     match self.id {
       _ => vec![Ability::NOTHING],
     }
   }
 
+  // Returns true the first time the unit is seen
+  pub fn mark_seen(&mut self) -> bool {
+    if self.seen {
+      return false;
+    }
+    self.seen = true;
+    return true;
+  }
+
   pub fn despawn(&self) -> bool {
     return self.despawn;
   }
 
   // Return whether unit attacks player or not
-  pub fn possible_move(&mut self, time: f64) -> bool {
+  pub fn possible_move(&mut self, time: f64, active: bool) -> bool {
     let mut attack_player = false;
-    let chance_moved = time * constants::UNIT_MOVE_CHANCE;
+    let mut chance_moved = time * constants::UNIT_MOVE_CHANCE;
+    if !active {
+      chance_moved *= constants::INACTIVE_MOVE_FACTOR;
+    }
     if random_chance(1.0 - chance_moved) {
       return attack_player;
     }
@@ -95,29 +148,99 @@ impl Unit {
 
   pub fn view_short(&self, loader: &UnitLoader) -> String {
     let data = loader.get_data(self.id);
+    if self.elite {
+      return format!("{} ({}) [ELITE]", data.name, self.level);
+    }
     return format!("{} ({})", data.name, self.level);
   }
 
+  // Name followed by what a player of the given level can tell about the unit
+  pub fn inspect(&self, loader: &UnitLoader, player_level: u8) -> String {
+    return self.view_short(loader) + &loader.get_data(self.id).inspect(self.level, player_level, self.stat_factor());
+  }
+
   pub fn file_content(&self) -> String {
     let mut contents = String::new();
     contents += &format!("\n   id: {}", self.id);
     contents += &format!("\n   position: {}", self.position);
     contents += &format!("\n   view_index: {}", self.view_index);
     contents += &format!("\n   despawn: {}", self.despawn);
+    contents += &format!("\n   seen: {}", self.seen);
+    contents += &format!("\n   elite: {}", self.elite);
+    contents += &format!("\n   announced: {}", self.announced);
     contents += &format!("\n   level: {}", self.level);
     return contents;
   }
 
-  pub fn read_line(&mut self, line: String) {
-    let (key, mut value) = line.split_once(":").unwrap();
-    value = value.trim();
-    match key.trim() {
-      "id"         => self.id         = value.parse::<u64>().unwrap_or(0),
-      "position"   => self.position   = Position::from_str(value).unwrap_or(Position::FAR),
-      "view_index" => self.view_index = value.parse::<i64>().unwrap_or(-1),
-      "despawn"    => self.despawn    = value.parse::<bool>().unwrap_or(true),
-      "level"      => self.level      = value.parse::<u8>().unwrap_or(0),
-      _ => {},
+  pub fn from_block(block: &RotfBlock) -> Result<Unit, RotfParseError> {
+    let mut unit = Unit::new(0, 0);
+    for line in &block.lines {
+      unit.read_line(line)?;
     }
+    return Ok(unit);
+  }
+
+  pub fn read_line(&mut self, line: &RotfLine) -> Result<(), RotfParseError> {
+    match line.key.as_str() {
+      "id"         => self.id         = line.parse::<u64>("an unsigned integer")?,
+      "position"   => self.position   = line.parse::<Position>("NEAR, MEDIUM or FAR")?,
+      "view_index" => self.view_index = line.parse::<i64>("an integer")?,
+      "despawn"    => self.despawn    = line.parse::<bool>("true or false")?,
+      "seen"       => self.seen       = line.parse::<bool>("true or false")?,
+      "elite"      => self.elite      = line.parse::<bool>("true or false")?,
+      "announced"  => self.announced  = line.parse::<bool>("true or false")?,
+      "level"      => self.level      = line.parse::<u8>("an integer from 0 to 255")?,
+      _ => return Err(line.unknown_key()),
+    }
+    Ok(())
   }
 }
+
+
+#[cfg(test)]
+pub mod test_unit {
+  use crate::test_main::test_paths;
+  use crate::game::unit::*;
+
+  #[test]
+  fn test_roll_elite() {
+    let rolls = 4000;
+    let mut elites = 0;
+    for _ in 0..rolls {
+      let mut unit = Unit::new(1, 5);
+      unit.roll_elite();
+      if unit.elite {
+        elites += 1;
+        assert_eq!(unit.level, 5 + constants::ELITE_LEVEL_BONUS);
+        // announced only once
+        assert!(unit.announce());
+        assert!(!unit.announce());
+      }
+      else {
+        assert_eq!(unit.level, 5);
+        assert!(!unit.announce());
+      }
+    }
+    // within a wide margin of the spawn chance so the test doesn't flake
+    let expected = rolls as f64 * constants::ELITE_SPAWN_CHANCE;
+    assert!((elites as f64) > expected * 0.5 && (elites as f64) < expected * 1.5, "{} elites in {} rolls", elites, rolls);
+    // the level bonus stops at the highest level
+    let mut unit = Unit::new(1, 255);
+    while !unit.elite {
+      unit.roll_elite();
+    }
+    assert_eq!(unit.level, 255);
+  }
+
+  #[test]
+  fn test_elite_stats() {
+    let mut loader = UnitLoader::new();
+    loader.load_data(&test_paths()).unwrap();
+    let mut grub = Unit::new(4, 2);
+    assert_eq!(grub.inspect(&loader, 2),
+      "Drowning Grub (2)\n  A fat beetle grub kicking at the surface\n  Health: 10, Attack: 2, Defense: 2\n  \"It was never meant to swim\"");
+    grub.elite = true;
+    assert_eq!(grub.inspect(&loader, 2),
+      "Drowning Grub (2) [ELITE]\n  A fat beetle grub kicking at the surface\n  Health: 15, Attack: 3, Defense: 3\n  \"It was never meant to swim\"");
+  }
+}
\ No newline at end of file
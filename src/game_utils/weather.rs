@@ -0,0 +1,187 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::context::constants;
+use crate::numeric::random_int;
+
+use super::traits::DamageType;
+
+
+// Season determined by the day of the game clock
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum Season {
+  SPRING,
+  SUMMER,
+  AUTUMN,
+  WINTER,
+}
+
+impl fmt::Display for Season {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl Season {
+  pub fn from_day(day: u64) -> Season {
+    match (day.saturating_sub(1) / constants::DAYS_PER_SEASON) % 4 {
+      0 => Season::SPRING,
+      1 => Season::SUMMER,
+      2 => Season::AUTUMN,
+      _ => Season::WINTER,
+    }
+  }
+}
+
+
+// Weather currently affecting the environment
+#[allow(non_camel_case_types)]
+#[derive(Debug, EnumIter, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Weather {
+  CLEAR,
+  RAIN,
+  DROUGHT,
+  COLD_SNAP,
+}
+
+impl fmt::Display for Weather {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for Weather {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for weather in Weather::iter() {
+      if weather.to_string() == s {
+        return Ok(weather);
+      }
+    }
+    Err(())
+  }
+}
+
+impl Weather {
+  pub fn default() -> Weather {
+    return Weather::CLEAR;
+  }
+
+  // Weights of each weather in the order CLEAR, RAIN, DROUGHT, COLD_SNAP
+  fn season_weights(season: &Season) -> [u64; 4] {
+    match season {
+      Season::SPRING => [4, 4, 1, 1],
+      Season::SUMMER => [5, 2, 3, 0],
+      Season::AUTUMN => [4, 4, 1, 1],
+      Season::WINTER => [4, 1, 0, 5],
+    }
+  }
+
+  pub fn random(season: &Season) -> Weather {
+    let weights = Weather::season_weights(season);
+    let mut roll = random_int(1, weights.iter().sum());
+    for (weather, weight) in Weather::iter().zip(weights) {
+      if roll <= weight {
+        return weather;
+      }
+      roll -= weight;
+    }
+    return Weather::default();
+  }
+
+  // Factor applied to the number of units in the environment
+  pub fn unit_factor(&self) -> f64 {
+    match self {
+      Weather::CLEAR => 1.0,
+      Weather::RAIN => 1.2,
+      Weather::DROUGHT => 0.6,
+      Weather::COLD_SNAP => 0.5,
+    }
+  }
+
+  // Factor applied to the number of items in the environment
+  pub fn item_factor(&self) -> f64 {
+    match self {
+      Weather::CLEAR => 1.0,
+      Weather::RAIN => 1.5,
+      Weather::DROUGHT => 0.5,
+      Weather::COLD_SNAP => 0.8,
+    }
+  }
+
+  // Factor applied to damage dealt of the given type
+  pub fn damage_factor(&self, damage_type: &DamageType) -> f64 {
+    match (self, damage_type) {
+      (_, DamageType::TRUE) => 1.0,
+      (Weather::RAIN, DamageType::PHYSICAL) => 0.9,
+      (Weather::RAIN, DamageType::MAGICAL) => 1.1,
+      (Weather::DROUGHT, DamageType::PHYSICAL) => 1.1,
+      (Weather::DROUGHT, DamageType::MAGICAL) => 0.9,
+      (Weather::COLD_SNAP, _) => 0.8,
+      _ => 1.0,
+    }
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_weather {
+  use rstest::*;
+  use crate::game::weather::*;
+
+  #[rstest]
+  #[case(0, Season::SPRING)]
+  #[case(1, Season::SPRING)]
+  #[case(constants::DAYS_PER_SEASON, Season::SPRING)]
+  #[case(constants::DAYS_PER_SEASON + 1, Season::SUMMER)]
+  #[case(2 * constants::DAYS_PER_SEASON + 1, Season::AUTUMN)]
+  #[case(4 * constants::DAYS_PER_SEASON, Season::WINTER)]
+  #[case(4 * constants::DAYS_PER_SEASON + 1, Season::SPRING)]
+  fn test_season_from_day(#[case] day: u64, #[case] season: Season) {
+    assert_eq!(Season::from_day(day), season);
+  }
+
+  #[test]
+  fn test_random_follows_season_weights() {
+    // weathers with no weight in a season never roll, and every other weather eventually does
+    for season in Season::iter() {
+      let weights = Weather::season_weights(&season);
+      let mut rolled = vec![false; weights.len()];
+      for _ in 0..1000 {
+        let weather = Weather::random(&season);
+        let index = Weather::iter().position(|w| w == weather).unwrap();
+        assert!(weights[index] > 0, "{} rolled in {}", weather, season);
+        rolled[index] = true;
+      }
+      assert_eq!(rolled, weights.iter().map(|weight| *weight > 0).collect::<Vec<bool>>());
+    }
+  }
+
+  #[test]
+  fn test_spawn_factors() {
+    assert_eq!((Weather::CLEAR.unit_factor(), Weather::CLEAR.item_factor()), (1.0, 1.0));
+    assert!(Weather::RAIN.unit_factor() > 1.0 && Weather::RAIN.item_factor() > 1.0);
+    for weather in [Weather::DROUGHT, Weather::COLD_SNAP] {
+      assert!(weather.unit_factor() < 1.0 && weather.item_factor() < 1.0);
+    }
+    assert_eq!(Weather::from_str("COLD_SNAP"), Ok(Weather::COLD_SNAP));
+    assert!(Weather::from_str("SNOW").is_err());
+  }
+
+  #[rstest]
+  #[case(Weather::CLEAR, DamageType::PHYSICAL, 1.0)]
+  #[case(Weather::RAIN, DamageType::PHYSICAL, 0.9)]
+  #[case(Weather::RAIN, DamageType::MAGICAL, 1.1)]
+  #[case(Weather::DROUGHT, DamageType::PHYSICAL, 1.1)]
+  #[case(Weather::DROUGHT, DamageType::MIXED, 1.0)]
+  #[case(Weather::COLD_SNAP, DamageType::MAGICAL, 0.8)]
+  #[case(Weather::COLD_SNAP, DamageType::TRUE, 1.0)]
+  fn test_damage_factor(#[case] weather: Weather, #[case] damage_type: DamageType, #[case] factor: f64) {
+    assert_eq!(weather.damage_factor(&damage_type), factor);
+  }
+}
@@ -2,26 +2,50 @@
 #[path = "command_utils/commands.rs"] mod commands;
 #[path = "game_utils/game.rs"] mod game;
 #[path = "game_utils/cutscene.rs"] mod cutscene;
+#[path = "game_utils/dialogue.rs"] mod dialogue;
 #[path = "utils/filesystem.rs"] mod filesystem;
 #[path = "utils/numeric.rs"] mod numeric;
+#[path = "utils/rotf_file.rs"] mod rotf_file;
+#[path = "utils/csv.rs"] mod csv;
 #[path = "options.rs"] mod options;
+#[path = "paths.rs"] mod paths;
 
 mod credits;
 
-use context::RotfContext;
+use context::{RotfContext, validation};
+use paths::RotfPaths;
 
+use std::env;
 use std::io::{self, BufRead, Write};
+use std::process;
 
 fn main() {
-  main_loop(io::stdin().lock(), io::stdout(), io::stderr(), false);
+  let mut args: Vec<String> = env::args().skip(1).collect();
+  // check the data and exit instead of starting the game
+  let validate = args.iter().any(|arg| arg == "--validate");
+  args.retain(|arg| arg != "--validate");
+  let paths = match RotfPaths::resolve(args) {
+    Ok(paths) => paths,
+    Err(e) => {
+      eprintln!("{}", e);
+      eprintln!("Usage: rise_of_the_frogs [--data-dir <folder>] [--save-dir <folder>] [--validate]");
+      process::exit(2);
+    },
+  };
+  if validate {
+    let issues = validation::validate(&paths);
+    println!("{}", validation::report(&issues));
+    process::exit(if validation::has_errors(&issues) { 1 } else { 0 });
+  }
+  main_loop(io::stdin().lock(), io::stdout(), io::stderr(), false, paths);
 }
 
-fn main_loop<R, W, E>(input: R, output: W, error: E, testing: bool) where
+fn main_loop<R, W, E>(input: R, output: W, error: E, testing: bool, paths: RotfPaths) where
   R: BufRead,
   W: Write,
   E: Write,
 {
-  let mut context = RotfContext::default_context(input, output, error, testing);
+  let mut context = RotfContext::default_context(input, output, error, testing, paths);
 
   credits::welcome(&mut context);
 
@@ -49,8 +73,72 @@ fn main_loop<R, W, E>(input: R, output: W, error: E, testing: bool) where
 #[cfg(test)]
 pub mod test_main {
   use std::str;
+  use std::env;
+  use std::process;
+  use std::sync::OnceLock;
+  use std::time::Duration;
   use std::io::{BufRead, Write};
   use crate::{main_loop, commands::parse_command, context::{RotfContext, test_context::TestContext}};
+  use crate::{filesystem, paths::RotfPaths, game::{RotfGame, RotfDifficulty}};
+
+  static TEST_PATHS: OnceLock<RotfPaths> = OnceLock::new();
+
+  // Game data from the repo with saves in a temporary folder holding the "test" saved game
+  pub fn test_paths() -> RotfPaths {
+    return TEST_PATHS.get_or_init(|| {
+      let save_root = env::temp_dir().join(format!("rotf_test_{}", process::id()));
+      // the shared save root is used until the test run exits, so clean up test roots of earlier runs instead
+      for entry in filesystem::open_folder(env::temp_dir().to_string_lossy().to_string()).unwrap_or_default() {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let age = entry.metadata().and_then(|m| m.modified()).map(|t| t.elapsed().unwrap_or_default());
+        if name.starts_with("rotf_test_") && entry != save_root && age.is_ok_and(|a| a > Duration::from_secs(600)) {
+          filesystem::delete_folder(entry.to_string_lossy().to_string()).unwrap_or_default();
+        }
+      }
+      let paths = RotfPaths::new("data".to_owned(), save_root.to_string_lossy().to_string());
+      if save_root.exists() {
+        filesystem::delete_folder(paths.saves()).unwrap();
+      }
+      filesystem::create_folder(paths.saves()).unwrap();
+      RotfGame::new("test".to_owned(), RotfDifficulty::default()).save(&paths).unwrap();
+      return paths;
+    }).clone();
+  }
+
+  // Data and saves in a temporary folder of a single test, deleted when dropped even if the test fails
+  pub struct TempRoot {
+    root: String,
+    pub paths: RotfPaths,
+  }
+
+  impl TempRoot {
+    // Game data and saves both in the temporary folder
+    pub fn new(name: &str) -> TempRoot {
+      let root = TempRoot::folder(name);
+      return TempRoot { paths: RotfPaths::new(root.clone(), format!("{}/saves", root)), root };
+    }
+
+    // Game data from the repo with saves in the temporary folder
+    pub fn saves(name: &str) -> TempRoot {
+      let root = TempRoot::folder(name);
+      return TempRoot { paths: RotfPaths::new("data".to_owned(), root.clone()), root };
+    }
+
+    fn folder(name: &str) -> String {
+      return env::temp_dir().join(format!("rotf_{}_{}", name, process::id())).to_string_lossy().to_string();
+    }
+
+    pub fn root(&self) -> String {
+      return self.root.clone();
+    }
+  }
+
+  impl Drop for TempRoot {
+    fn drop(&mut self) {
+      // the test may not have created the folder
+      filesystem::delete_folder(self.root()).unwrap_or_default();
+    }
+  }
 
   pub fn run_main_loop(mut input: Vec<&str>) -> (String, String) {
     input.push("exit");
@@ -58,7 +146,7 @@ pub mod test_main {
     let input_stream = binding.as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    main_loop(&input_stream[..], &mut output, &mut error, false);
+    main_loop(&input_stream[..], &mut output, &mut error, false, test_paths());
     let binding = output.clone();
     let output_str = str::from_utf8(&binding).unwrap();
     let binding = error.clone();
@@ -70,7 +158,7 @@ pub mod test_main {
     let input = "".as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     parse_command(cmd, &mut context);
     let binding = output.clone();
     let output_str = str::from_utf8(&binding).unwrap();
@@ -83,7 +171,7 @@ pub mod test_main {
     let input = extra_input.as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     parse_command(cmd, &mut context);
     let binding = output.clone();
     let output_str = str::from_utf8(&binding).unwrap();
@@ -96,7 +184,7 @@ pub mod test_main {
     let input = "".as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     parse_command(cmd, &mut context);
     return TestContext::new(context);
   }
@@ -105,7 +193,7 @@ pub mod test_main {
     let input = extra_input.as_bytes();
     let mut output = Vec::new();
     let mut error = Vec::new();
-    let mut context = RotfContext::default(&input[..], &mut output, &mut error);
+    let mut context = RotfContext::default(&input[..], &mut output, &mut error, test_paths());
     parse_command(cmd, &mut context);
     return TestContext::new(context);
   }
@@ -1,17 +1,68 @@
+use crate::context::constants;
 use crate::filesystem;
+use crate::paths::RotfPaths;
+use crate::game::save_backend::SaveFormat;
+
+use std::{fmt, io::BufRead, path::Path, str::FromStr};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+
+// When a game in progress is written to disk without using 'save'
+#[allow(non_camel_case_types)]
+#[derive(Debug, EnumIter, PartialEq, Clone)]
+pub enum AutosavePolicy {
+  EVERY_COMMAND, // after every command that changed the game
+  EVERY_N_TURNS, // after autosave_turns in-game commands
+  STATE_CHANGE, // when the game enters or leaves a cutscene, environment or combat
+  MANUAL, // only with 'save' and on exit
+}
+
+impl fmt::Display for AutosavePolicy {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for AutosavePolicy {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    for policy in AutosavePolicy::iter() {
+      if policy.to_string() == s.to_uppercase() {
+        return Ok(policy);
+      }
+    }
+    Err(())
+  }
+}
+
+impl AutosavePolicy {
+  pub fn default() -> AutosavePolicy {
+    return AutosavePolicy::EVERY_COMMAND;
+  }
+}
 
-use std::{io::BufRead, path::Path};
 
 pub struct RotfOptions {
   pub sleep_factor: f64,
+  pub save_format: SaveFormat,
+  pub trash_size: usize,
+  pub autosave: AutosavePolicy,
+  pub autosave_turns: usize,
 }
 
 impl RotfOptions {
-  pub fn default() -> RotfOptions {
+  pub fn default(paths: &RotfPaths) -> RotfOptions {
     let mut options = RotfOptions {
       sleep_factor: 1.0,
+      save_format: SaveFormat::default(),
+      trash_size: constants::DEFAULT_TRASH_SIZE,
+      autosave: AutosavePolicy::default(),
+      autosave_turns: constants::DEFAULT_AUTOSAVE_TURNS,
     };
-    match filesystem::open_file("data/saves/options.rotf".to_owned()) {
+    match filesystem::open_file(paths.options_file()) {
       Ok(f) => {
         for oline in f.lines() {
           let line = oline.unwrap_or("".to_owned());
@@ -22,21 +73,29 @@ impl RotfOptions {
           value = value.trim();
           match key.trim() {
             "sleep_factor" => options.sleep_factor = value.parse::<f64>().unwrap_or(1.0),
+            "save_format" => options.save_format = SaveFormat::from_str(value).unwrap_or(SaveFormat::default()),
+            "trash_size" => options.trash_size = value.parse::<usize>().unwrap_or(constants::DEFAULT_TRASH_SIZE),
+            "autosave" => options.autosave = AutosavePolicy::from_str(value).unwrap_or(AutosavePolicy::default()),
+            "autosave_turns" => options.autosave_turns = match value.parse::<usize>() {
+              Ok(turns) if turns > 0 => turns,
+              _ => constants::DEFAULT_AUTOSAVE_TURNS,
+            },
             _ => {},
           }
         }
       },
       Err(_e) => {
-        if !Path::new("data/saves/options.rotf").exists() {
-          options.save();
+        if !Path::new(&paths.options_file()).exists() {
+          options.save(paths);
         }
       },
     }
     return options;
   }
 
-  pub fn save(&self) {
-    match filesystem::create_file("data/saves/options.rotf".to_owned(), self.file_content()) {
+  pub fn save(&self, paths: &RotfPaths) {
+    match filesystem::create_folder(paths.saves())
+      .and_then(|_| filesystem::create_file(paths.options_file(), self.file_content())) {
       _ => {},
     }
   }
@@ -44,6 +103,10 @@ impl RotfOptions {
   pub fn file_content(&self) -> String {
     let mut contents = String::new();
     contents += &format!("\nsleep_factor: {}", self.sleep_factor.clone());
+    contents += &format!("\nsave_format: {}", self.save_format);
+    contents += &format!("\ntrash_size: {}", self.trash_size);
+    contents += &format!("\nautosave: {}", self.autosave);
+    contents += &format!("\nautosave_turns: {}", self.autosave_turns);
     return contents;
   }
 }
@@ -0,0 +1,176 @@
+use std::env;
+use std::path::Path;
+
+
+// Name of the folder used under the XDG data directory
+const APP_FOLDER: &str = "rise_of_the_frogs";
+
+
+// Root folders for game data and saved games; every file path is built from these
+#[derive(Clone, Debug, PartialEq)]
+pub struct RotfPaths {
+  pub data_root: String,
+  pub save_root: String,
+}
+
+impl RotfPaths {
+  pub fn new(data_root: String, save_root: String) -> RotfPaths {
+    return RotfPaths {
+      data_root,
+      save_root,
+    }
+  }
+
+  // Resolves the roots from command line flags, then environment variables, then defaults
+  pub fn resolve(args: Vec<String>) -> Result<RotfPaths, String> {
+    let mut data_root = env::var("ROTF_DATA_DIR").ok();
+    let mut save_root = env::var("ROTF_SAVE_DIR").ok();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+      let (flag, value) = match arg.split_once("=") {
+        Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+        None => (arg.clone(), None),
+      };
+      let root = match flag.as_str() {
+        "--data-dir" => &mut data_root,
+        "--save-dir" => &mut save_root,
+        _ => return Err(format!("Unrecognized argument '{}'", arg)),
+      };
+      match value.or_else(|| args.next()) {
+        Some(v) if !v.is_empty() => *root = Some(v),
+        _ => return Err(format!("Missing a folder after '{}'", flag)),
+      }
+    }
+    let data_root = data_root.unwrap_or_else(RotfPaths::default_data_root);
+    let save_root = save_root.unwrap_or_else(|| RotfPaths::default_save_root(&data_root));
+    return Ok(RotfPaths::new(data_root, save_root));
+  }
+
+  // $XDG_DATA_HOME/rise_of_the_frogs, falling back to ~/.local/share/rise_of_the_frogs
+  fn xdg_root() -> Option<String> {
+    match env::var("XDG_DATA_HOME") {
+      Ok(dir) if !dir.is_empty() => return Some(format!("{}/{}", dir, APP_FOLDER)),
+      _ => {},
+    }
+    match env::var("HOME") {
+      Ok(dir) if !dir.is_empty() => Some(format!("{}/.local/share/{}", dir, APP_FOLDER)),
+      _ => None,
+    }
+  }
+
+  // The data folder in the current directory, then next to the executable, then the XDG data folder
+  fn default_data_root() -> String {
+    if Path::new("data").is_dir() {
+      return "data".to_owned();
+    }
+    match env::current_exe() {
+      Ok(exe) => match exe.parent().map(|dir| dir.join("data")) {
+        Some(dir) if dir.is_dir() => return dir.to_string_lossy().to_string(),
+        _ => {},
+      },
+      Err(_) => {},
+    }
+    return RotfPaths::xdg_root().unwrap_or("data".to_owned());
+  }
+
+  // Existing saves in the data root are kept where they are, otherwise the XDG data folder is used
+  fn default_save_root(data_root: &str) -> String {
+    let legacy = format!("{}/saves", data_root);
+    if Path::new(&legacy).is_dir() {
+      return legacy;
+    }
+    match RotfPaths::xdg_root() {
+      Some(root) => format!("{}/saves", root),
+      None => legacy,
+    }
+  }
+
+  pub fn units_file(&self) -> String {
+    return format!("{}/units/data.csv", self.data_root);
+  }
+
+  pub fn items_file(&self) -> String {
+    return format!("{}/items/data.csv", self.data_root);
+  }
+
+  pub fn units_templates_file(&self) -> String {
+    return format!("{}/units/templates.csv", self.data_root);
+  }
+
+  pub fn items_templates_file(&self) -> String {
+    return format!("{}/items/templates.csv", self.data_root);
+  }
+
+  pub fn cutscene_file(&self, cutscene: &str) -> String {
+    return format!("{}/cutscenes/{}.rotf", self.data_root, cutscene);
+  }
+
+  pub fn npcs(&self) -> String {
+    return format!("{}/npcs", self.data_root);
+  }
+
+  pub fn npc_file(&self, npc: &str) -> String {
+    return format!("{}/npcs/{}.rotf", self.data_root, npc);
+  }
+
+  pub fn mods(&self) -> String {
+    return format!("{}/mods", self.data_root);
+  }
+
+  // Paths to a content pack's data, which is laid out like the base data
+  pub fn content_pack(&self, name: &str) -> RotfPaths {
+    return RotfPaths::new(format!("{}/{}", self.mods(), name), self.save_root.clone());
+  }
+
+  pub fn content_pack_file(&self, name: &str) -> String {
+    return format!("{}/{}/mod.rotf", self.mods(), name);
+  }
+
+  pub fn options_file(&self) -> String {
+    return format!("{}/options.rotf", self.save_root);
+  }
+
+  pub fn saves(&self) -> String {
+    return self.save_root.clone();
+  }
+
+  pub fn save(&self, save_name: &str) -> String {
+    return format!("{}/{}", self.save_root, save_name);
+  }
+
+  pub fn save_file(&self, save_name: &str, file_name: &str) -> String {
+    return format!("{}/{}/{}", self.save_root, save_name, file_name);
+  }
+
+  pub fn exports(&self) -> String {
+    return format!("{}/.exports", self.save_root);
+  }
+
+  pub fn export_file(&self, name: &str) -> String {
+    return format!("{}/{}.json", self.exports(), name);
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_paths {
+  use crate::paths::RotfPaths;
+
+  fn args(args: &str) -> Vec<String> {
+    return args.split_whitespace().map(|a| a.to_owned()).collect();
+  }
+
+  #[test]
+  fn test_resolve_flags() {
+    let paths = RotfPaths::resolve(args("--data-dir /a/data --save-dir=/b/saves")).unwrap();
+    assert_eq!(paths, RotfPaths::new("/a/data".to_owned(), "/b/saves".to_owned()));
+    assert_eq!(paths.save_file("my_game", "player.rotf"), "/b/saves/my_game/player.rotf");
+    assert_eq!(paths.cutscene_file("LAUNCH_GAME"), "/a/data/cutscenes/LAUNCH_GAME.rotf");
+  }
+
+  #[test]
+  fn test_resolve_invalid_flags() {
+    assert_eq!(RotfPaths::resolve(args("--data-dir")), Err("Missing a folder after '--data-dir'".to_owned()));
+    assert_eq!(RotfPaths::resolve(args("--unknown")), Err("Unrecognized argument '--unknown'".to_owned()));
+  }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::filesystem;
+
+
+// Error describing where and why a .csv file failed to parse
+#[derive(Debug)]
+pub struct CsvParseError {
+  pub file: String,
+  pub row: usize,
+  pub column: String,
+  pub expected: String,
+}
+
+impl fmt::Display for CsvParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.column.is_empty() {
+      write!(f, "{} row {}: expected {}", self.file, self.row, self.expected)
+    }
+    else {
+      write!(f, "{} row {}: column '{}' expected {}", self.file, self.row, self.column, self.expected)
+    }
+  }
+}
+
+impl Error for CsvParseError {}
+
+impl From<CsvParseError> for io::Error {
+  fn from(e: CsvParseError) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, e);
+  }
+}
+
+
+// A single data row, with fields keyed by the column names in the header row
+#[derive(Clone)]
+pub struct CsvRow {
+  pub file: String,
+  pub row: usize,
+  fields: HashMap<String, String>,
+}
+
+impl CsvRow {
+  // Value of a column, which is empty if the row doesn't have it
+  pub fn get(&self, column: &str) -> &str {
+    return self.fields.get(column).map(|s| s.as_str()).unwrap_or("");
+  }
+
+  pub fn set(&mut self, column: &str, value: String) {
+    self.fields.insert(column.to_owned(), value);
+  }
+
+  pub fn parse<T: FromStr>(&self, column: &str, expected: &str) -> Result<T, CsvParseError> {
+    return self.parse_with(column, expected, |s| T::from_str(s).map_err(|_| ()));
+  }
+
+  // Parses a column, using the default when it's empty
+  pub fn parse_or<T: FromStr>(&self, column: &str, default: T, expected: &str) -> Result<T, CsvParseError> {
+    if self.get(column).is_empty() {
+      return Ok(default);
+    }
+    return self.parse(column, expected);
+  }
+
+  pub fn parse_with<T, F>(&self, column: &str, expected: &str, parser: F) -> Result<T, CsvParseError> where
+    F: Fn(&str) -> Result<T, ()>,
+  {
+    return parser(self.get(column)).map_err(|_| self.error(column, expected));
+  }
+
+  // Parses a ';' separated list, where an empty column is an empty list
+  pub fn parse_list<T: FromStr>(&self, column: &str, expected: &str) -> Result<Vec<T>, CsvParseError> {
+    let mut list = Vec::new();
+    for value in self.get(column).split(";").map(|s| s.trim()).filter(|s| !s.is_empty()) {
+      match T::from_str(value) {
+        Ok(v) => list.push(v),
+        Err(_) => return Err(self.error(column, expected)),
+      }
+    }
+    return Ok(list);
+  }
+
+  pub fn error(&self, column: &str, expected: &str) -> CsvParseError {
+    return CsvParseError {
+      file: self.file.clone(),
+      row: self.row,
+      column: column.to_owned(),
+      expected: format!("{}, found '{}'", expected, self.get(column)),
+    };
+  }
+}
+
+
+pub fn parse_file(path: String, required: &[&str]) -> Result<Vec<CsvRow>, io::Error> {
+  let contents = filesystem::read_file(path.clone())?;
+  return Ok(parse(&path, &contents, required)?);
+}
+
+// Parses rows keyed by the header row, skipping rows where every field is empty;
+// the header must contain every required column
+pub fn parse(file: &str, contents: &str, required: &[&str]) -> Result<Vec<CsvRow>, CsvParseError> {
+  let error = |row: usize, column: &str, expected: String| CsvParseError {
+    file: file.to_owned(),
+    row,
+    column: column.to_owned(),
+    expected,
+  };
+  let mut lines = contents.lines().enumerate();
+  let header = match lines.next() {
+    Some((_, line)) => split_row(line).map_err(|e| error(1, "", e))?,
+    None => return Err(error(1, "", "a header row".to_owned())),
+  };
+  for (i, column) in header.iter().enumerate() {
+    if header[..i].contains(column) {
+      return Err(error(1, column, "to appear only once in the header".to_owned()));
+    }
+  }
+  for column in required {
+    if !header.iter().any(|c| c == column) {
+      return Err(error(1, column, "to be in the header".to_owned()));
+    }
+  }
+  let mut rows = Vec::new();
+  for (i, line) in lines {
+    let row_number = i + 1;
+    let values = split_row(line).map_err(|e| error(row_number, "", e))?;
+    if values.len() > header.len() {
+      return Err(error(row_number, "", format!("at most {} fields, found {}", header.len(), values.len())));
+    }
+    if values.iter().all(|v| v.is_empty()) {
+      continue;
+    }
+    rows.push(CsvRow {
+      file: file.to_owned(),
+      row: row_number,
+      fields: header.iter().cloned().zip(values).collect(),
+    });
+  }
+  return Ok(rows);
+}
+
+// Fills empty values in a column with the value from the row above, so a value applies to every row
+// below it until the next one
+pub fn carry_forward(rows: &mut Vec<CsvRow>, column: &str) {
+  let mut value = String::new();
+  for row in rows.iter_mut() {
+    match row.fields.get(column) {
+      Some(v) if !v.is_empty() => value = v.clone(),
+      _ => {
+        row.fields.insert(column.to_owned(), value.clone());
+      },
+    }
+  }
+}
+
+// Splits a line on ',' and trims each field; fields in double quotes can contain ',' and '""' for a quote
+fn split_row(line: &str) -> Result<Vec<String>, String> {
+  let mut fields = Vec::new();
+  let mut chars = line.chars().peekable();
+  loop {
+    let mut field = String::new();
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+      chars.next();
+    }
+    if chars.peek() == Some(&'"') {
+      chars.next();
+      loop {
+        match chars.next() {
+          Some('"') if chars.peek() == Some(&'"') => {
+            chars.next();
+            field.push('"');
+          },
+          Some('"') => break,
+          Some(c) => field.push(c),
+          None => return Err(format!("a closing quote in field {}", fields.len() + 1)),
+        }
+      }
+      while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+      }
+      match chars.peek() {
+        Some(',') | None => {},
+        Some(_) => return Err(format!("',' after the closing quote in field {}", fields.len() + 1)),
+      }
+    }
+    else {
+      while let Some(c) = chars.next_if(|c| *c != ',') {
+        field.push(c);
+      }
+      field = field.trim().to_owned();
+    }
+    fields.push(field);
+    if chars.next().is_none() {
+      return Ok(fields);
+    }
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_csv {
+  use crate::csv::*;
+
+  #[test]
+  fn test_parse_header_columns() {
+    let rows = parse("test.csv", "ID ,Name ,Rarity\n 1 ,Frog ,3\n   ,     ,\n 2 ,\"Frog, Big \"\"Bully\"\"\" \n", &["ID", "Name"]).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get("Name"), "Frog");
+    assert_eq!(rows[0].parse::<u32>("Rarity", "an unsigned integer").unwrap(), 3);
+    assert_eq!(rows[1].row, 4);
+    assert_eq!(rows[1].get("Name"), "Frog, Big \"Bully\"");
+    assert_eq!(rows[1].get("Rarity"), "");
+    assert_eq!(rows[1].parse_or::<u32>("Rarity", 10, "an unsigned integer").unwrap(), 10);
+  }
+
+  #[test]
+  fn test_carry_forward() {
+    let mut rows = parse("test.csv", "Tier ,ID
+     ,0
+ 1   ,1
+     ,2
+ 2   ,
+     ,3", &[]).unwrap();
+    carry_forward(&mut rows, "Tier");
+    let tiers: Vec<&str> = rows.iter().map(|row| row.get("Tier")).collect();
+    assert_eq!(tiers, vec!["", "1", "1", "2", "2"]);
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    let e = parse("test.csv", "ID ,Name\n1 ,Frog\n2 ,\"Frog", &[]).err().unwrap();
+    assert_eq!(e.to_string(), "test.csv row 3: expected a closing quote in field 2");
+    let e = parse("test.csv", "ID ,Name\n1 ,Frog ,3", &[]).err().unwrap();
+    assert_eq!(e.to_string(), "test.csv row 2: expected at most 2 fields, found 3");
+    let e = parse("test.csv", "ID ,Level", &["Name"]).err().unwrap();
+    assert_eq!(e.to_string(), "test.csv row 1: column 'Name' expected to be in the header");
+    let rows = parse("test.csv", "ID ,Name\none ,Frog", &[]).unwrap();
+    assert_eq!(rows[0].parse::<u64>("ID", "an unsigned integer").err().unwrap().to_string(),
+      "test.csv row 2: column 'ID' expected an unsigned integer, found 'one'");
+  }
+}
@@ -1,4 +1,4 @@
-use std::{fs, io::{self, Error, BufReader}, path::PathBuf};
+use std::{fs, io::{self, Error, BufReader, Write}, path::PathBuf};
 
 pub fn open_file(path: String) -> Result<BufReader<fs::File>, Error> {
   let file = fs::File::open(path)?;
@@ -30,6 +30,37 @@ pub fn create_file(path: String, contents: String) -> Result<(), Error> {
   fs::write(path, contents)
 }
 
+// Writes to a .part file that is synced then renamed, so the file either exists complete or not at all
+pub fn create_file_synced(path: String, contents: String) -> Result<(), Error> {
+  let part = format!("{}.part", path);
+  let mut file = fs::File::create(part.clone())?;
+  file.write_all(contents.as_bytes())?;
+  file.sync_all()?;
+  fs::rename(part, path)
+}
+
+// Flushes a folder entry to disk; only unix allows opening folders for this
+pub fn sync(path: String) -> Result<(), Error> {
+  if PathBuf::from(&path).is_dir() && !cfg!(unix) {
+    return Ok(());
+  }
+  fs::File::open(path)?.sync_all()
+}
+
+// Syncs every file under a folder, then the folder itself
+pub fn sync_folder(path: String) -> Result<(), Error> {
+  for entry in open_folder(path.clone())? {
+    let entry = entry.to_string_lossy().to_string();
+    if PathBuf::from(&entry).is_dir() {
+      sync_folder(entry)?;
+    }
+    else {
+      sync(entry)?;
+    }
+  }
+  sync(path)
+}
+
 pub fn create_folder(path: String) -> Result<(), Error> {
   fs::create_dir_all(path)
 }
@@ -37,3 +68,25 @@ pub fn create_folder(path: String) -> Result<(), Error> {
 pub fn delete_folder(path: String) -> Result<(), Error> {
   fs::remove_dir_all(path)
 }
+
+pub fn read_file(path: String) -> Result<String, Error> {
+  fs::read_to_string(path)
+}
+
+pub fn rename(from: String, to: String) -> Result<(), Error> {
+  fs::rename(from, to)
+}
+
+pub fn copy_folder(from: String, to: String) -> Result<(), Error> {
+  create_folder(to.clone())?;
+  for entry in open_folder(from)? {
+    let name = entry.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if entry.is_dir() {
+      copy_folder(entry.to_string_lossy().to_string(), format!("{}/{}", to, name))?;
+    }
+    else {
+      fs::copy(entry, format!("{}/{}", to, name))?;
+    }
+  }
+  Ok(())
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use rand::Rng;
 
 
@@ -18,11 +20,21 @@ pub fn random_int<T>(min: T, max: T) -> T where
 
 
 // Struct representing an integer range
+#[derive(Debug, PartialEq)]
 pub struct IntegerRange {
   start: i64,
   end: i64,
 }
 
+impl fmt::Display for IntegerRange {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.start == self.end {
+      return write!(f, "{}", self.start);
+    }
+    write!(f, "{}-{}", self.start, self.end)
+  }
+}
+
 impl IntegerRange {
   pub fn new() -> IntegerRange {
     return IntegerRange {
@@ -31,19 +43,24 @@ impl IntegerRange {
     }
   }
 
-  pub fn from_str(s: &str) -> IntegerRange {
-    let mut range = IntegerRange::new();
-    if s.contains("-") {
-      let (start, end) = s.split_once("-").unwrap();
-      range.start = start.parse::<i64>().unwrap_or(-1);
-      range.end = end.parse::<i64>().unwrap_or(-1);
+  // Parses 'n' or 'n-m' where n <= m
+  pub fn parse(s: &str) -> Result<IntegerRange, ()> {
+    let (start, end) = s.split_once("-").unwrap_or((s, s));
+    let range = IntegerRange {
+      start: start.trim().parse::<i64>().map_err(|_| ())?,
+      end: end.trim().parse::<i64>().map_err(|_| ())?,
+    };
+    if range.start > range.end {
+      return Err(());
     }
-    else {
-      let num = s.parse::<i64>().unwrap_or(-1);
-      range.start = num;
-      range.end = num;
+    return Ok(range);
+  }
+
+  pub fn between(start: i64, end: i64) -> IntegerRange {
+    return IntegerRange {
+      start,
+      end,
     }
-    return range;
   }
 
   pub fn contains(&self, x: i64) -> bool {
@@ -57,4 +74,8 @@ impl IntegerRange {
   pub fn max(&self) -> i64 {
     return self.end;
   }
+
+  pub fn overlaps(&self, other: &IntegerRange) -> bool {
+    return self.start <= other.end && other.start <= self.end;
+  }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::filesystem;
+
+
+// Error describing where and why a .rotf file failed to parse
+#[derive(Debug)]
+pub struct RotfParseError {
+  pub file: String,
+  pub line: usize,
+  pub key: String,
+  pub expected: String,
+}
+
+impl fmt::Display for RotfParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.key.is_empty() {
+      write!(f, "{} line {}: expected {}", self.file, self.line, self.expected)
+    }
+    else {
+      write!(f, "{} line {}: key '{}' expected {}", self.file, self.line, self.key, self.expected)
+    }
+  }
+}
+
+impl Error for RotfParseError {}
+
+impl From<RotfParseError> for io::Error {
+  fn from(e: RotfParseError) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, e);
+  }
+}
+
+
+// A single 'key: value' line
+pub struct RotfLine {
+  pub file: String,
+  pub line: usize,
+  pub key: String,
+  pub value: String,
+}
+
+impl RotfLine {
+  pub fn parse<T: FromStr>(&self, expected: &str) -> Result<T, RotfParseError> {
+    return T::from_str(&self.value).map_err(|_| self.error(expected));
+  }
+
+  pub fn error(&self, expected: &str) -> RotfParseError {
+    return RotfParseError {
+      file: self.file.clone(),
+      line: self.line,
+      key: self.key.clone(),
+      expected: format!("{}, found '{}'", expected, self.value),
+    };
+  }
+
+  pub fn unknown_key(&self) -> RotfParseError {
+    return RotfParseError {
+      file: self.file.clone(),
+      line: self.line,
+      key: self.key.clone(),
+      expected: "to be a known key".to_owned(),
+    };
+  }
+}
+
+
+// Lines between '%%% BEGIN {kind}' and '%%% END {kind}'
+pub struct RotfBlock {
+  pub file: String,
+  pub kind: String,
+  pub line: usize,
+  pub lines: Vec<RotfLine>,
+}
+
+impl RotfBlock {
+  // Removes and returns a required line from the block
+  pub fn take(&mut self, key: &str) -> Result<RotfLine, RotfParseError> {
+    match self.lines.iter().position(|line| line.key == key) {
+      Some(i) => Ok(self.lines.remove(i)),
+      None => Err(RotfParseError {
+        file: self.file.clone(),
+        line: self.line,
+        key: key.to_owned(),
+        expected: format!("to be in {} block", self.kind),
+      }),
+    }
+  }
+
+  pub fn unknown_kind(&self) -> RotfParseError {
+    return RotfParseError {
+      file: self.file.clone(),
+      line: self.line,
+      key: "".to_owned(),
+      expected: format!("a known block kind, found '{}'", self.kind),
+    };
+  }
+}
+
+
+// Top-level entry of a .rotf file
+pub enum RotfEntry {
+  Line(RotfLine),
+  Block(RotfBlock),
+}
+
+
+pub fn parse_file(path: String) -> Result<Vec<RotfEntry>, io::Error> {
+  let contents = filesystem::read_file(path.clone())?;
+  return Ok(parse(&path, &contents)?);
+}
+
+pub fn parse(file: &str, contents: &str) -> Result<Vec<RotfEntry>, RotfParseError> {
+  let mut entries = Vec::new();
+  let mut block: Option<RotfBlock> = None;
+  let error = |line: usize, expected: &str| RotfParseError {
+    file: file.to_owned(),
+    line,
+    key: "".to_owned(),
+    expected: expected.to_owned(),
+  };
+  for (i, raw_line) in contents.lines().enumerate() {
+    let line_number = i + 1;
+    let line = raw_line.trim();
+    if line.is_empty() {
+      continue;
+    }
+    if let Some(kind) = line.strip_prefix("%%% BEGIN ") {
+      if block.is_some() {
+        return Err(error(line_number, "'%%% END' before another '%%% BEGIN'"));
+      }
+      block = Some(RotfBlock {
+        file: file.to_owned(),
+        kind: kind.trim().to_owned(),
+        line: line_number,
+        lines: Vec::new(),
+      });
+      continue;
+    }
+    if let Some(kind) = line.strip_prefix("%%% END ") {
+      match block.take() {
+        Some(b) => {
+          if b.kind != kind.trim() {
+            return Err(error(line_number, &format!("'%%% END {}'", b.kind)));
+          }
+          entries.push(RotfEntry::Block(b));
+        },
+        None => return Err(error(line_number, "'%%% BEGIN' before '%%% END'")),
+      }
+      continue;
+    }
+    let (key, value) = match line.split_once(":") {
+      Some(kv) => kv,
+      None => return Err(error(line_number, "a line of the form 'key: value'")),
+    };
+    let rotf_line = RotfLine {
+      file: file.to_owned(),
+      line: line_number,
+      key: key.trim().to_owned(),
+      value: value.trim().to_owned(),
+    };
+    match block.as_mut() {
+      Some(b) => b.lines.push(rotf_line),
+      None => entries.push(RotfEntry::Line(rotf_line)),
+    }
+  }
+  match block {
+    Some(b) => Err(error(b.line, &format!("'%%% END {}' to close block", b.kind))),
+    None => Ok(entries),
+  }
+}
+
+
+#[cfg(test)]
+pub mod test_rotf_file {
+  use crate::rotf_file::*;
+
+  #[test]
+  fn test_parse_lines_and_blocks() {
+    let entries = parse("test.rotf", "\nlevel: 3\n\n%%% BEGIN ITEM\n   id: 1\n%%% END ITEM\n").unwrap();
+    assert_eq!(entries.len(), 2);
+    match &entries[1] {
+      RotfEntry::Block(block) => {
+        assert_eq!(block.kind, "ITEM");
+        assert_eq!(block.lines[0].key, "id");
+        assert_eq!(block.lines[0].line, 5);
+      },
+      RotfEntry::Line(_) => panic!("expected block"),
+    }
+  }
+
+  #[test]
+  fn test_parse_missing_colon() {
+    let e = parse("test.rotf", "\nlevel: 3\nlevel 4").err().unwrap();
+    assert_eq!(e.line, 3);
+    assert_eq!(e.to_string(), "test.rotf line 3: expected a line of the form 'key: value'");
+  }
+
+  #[test]
+  fn test_parse_unclosed_block() {
+    let e = parse("test.rotf", "%%% BEGIN UNIT\n   id: 1").err().unwrap();
+    assert_eq!(e.line, 1);
+  }
+
+  #[test]
+  fn test_parse_value() {
+    let entries = parse("test.rotf", "level: high").unwrap();
+    match &entries[0] {
+      RotfEntry::Line(line) => {
+        let e = line.parse::<u8>("an integer from 0 to 255").err().unwrap();
+        assert_eq!(e.to_string(), "test.rotf line 1: key 'level' expected an integer from 0 to 255, found 'high'");
+      },
+      RotfEntry::Block(_) => panic!("expected line"),
+    }
+  }
+}